@@ -8,11 +8,10 @@ pub struct ChromosomeData {
 
 impl ChromosomeData {
     pub fn from(chrom: &str, chrom_idx: u8) -> Self {
-        let cd = ChromosomeData {
+        ChromosomeData {
             chrom: chrom.to_string(),
             index: chrom_idx,
-        };
-        cd
+        }
     }
 
     // Uncomment if needed. This can be useful for debugging
@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use roaring::RoaringBitmap;
+use serde::{Deserialize, Serialize};
+
+use crate::data_structures::{BucketLoc, CoverageData, FacetCoverage};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BucketStatistic {
+    Count,
+    MaxNegLogSignificance,
+    MaxEffectSize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BucketAggregate {
+    pub count: u32,
+    // NaN if none of the bucket's observations have a significance
+    pub max_neg_log_significance: f64,
+    // The effect size furthest from 0, with its sign. NaN if none of the bucket's observations
+    // have an effect size.
+    pub max_effect_size: f32,
+}
+
+impl BucketAggregate {
+    pub fn statistic(&self, statistic: BucketStatistic) -> f64 {
+        match statistic {
+            BucketStatistic::Count => self.count as f64,
+            BucketStatistic::MaxNegLogSignificance => self.max_neg_log_significance,
+            BucketStatistic::MaxEffectSize => self.max_effect_size as f64,
+        }
+    }
+}
+
+impl CoverageData {
+    // Aggregates of the observations at the given positions by the bucket of their source or
+    // target, in genome order
+    pub fn bucket_aggregates(
+        &self,
+        positions: &RoaringBitmap,
+        coverage: FacetCoverage,
+    ) -> BTreeMap<BucketLoc, BucketAggregate> {
+        let mut aggregates: BTreeMap<BucketLoc, BucketAggregate> = BTreeMap::new();
+        for position in positions {
+            let observation = match self.observation(position) {
+                Some(observation) => observation,
+                None => continue,
+            };
+            let bucket = match observation
+                .feature_id(coverage)
+                .and_then(|feature_id| self.feature_buckets.get(&feature_id))
+            {
+                Some(bucket) => *bucket,
+                None => continue,
+            };
+
+            let aggregate = aggregates.entry(bucket).or_insert(BucketAggregate {
+                count: 0,
+                max_neg_log_significance: f64::NAN,
+                max_effect_size: f32::NAN,
+            });
+            aggregate.count += 1;
+            // f64::max ignores NaN
+            aggregate.max_neg_log_significance = aggregate
+                .max_neg_log_significance
+                .max(observation.neg_log_significance);
+            if aggregate.max_effect_size.is_nan()
+                || observation.effect_size.abs() > aggregate.max_effect_size.abs()
+            {
+                aggregate.max_effect_size = observation.effect_size;
+            }
+        }
+        aggregates
+    }
+
+    // Writes one bedGraph line per bucket with observations, preceded by a track line if there's
+    // a track name. Buckets without a value for the statistic are left out.
+    pub fn write_bedgraph<W: Write>(
+        &self,
+        writer: &mut W,
+        positions: &RoaringBitmap,
+        coverage: FacetCoverage,
+        statistic: BucketStatistic,
+        track_name: Option<&str>,
+    ) -> io::Result<()> {
+        if let Some(track_name) = track_name {
+            writeln!(writer, "track type=bedGraph name=\"{}\"", track_name)?;
+        }
+
+        let bucket_size = self.bucket_size as u64;
+        for (bucket, aggregate) in self.bucket_aggregates(positions, coverage) {
+            let value = aggregate.statistic(statistic);
+            let chrom = match self.chrom_name(bucket.chrom) {
+                Some(chrom) if !value.is_nan() => chrom,
+                _ => continue,
+            };
+            let start = bucket.idx as u64 * bucket_size;
+            let mut end = start + bucket_size;
+            // The last bucket ends with the chromosome
+            if let Some(length) = self.chrom_lengths.get(bucket.chrom as usize) {
+                end = end.min(*length as u64);
+            }
+            writeln!(writer, "{}\t{}\t{}\t{}", chrom, start, end, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BucketStatistic;
+    use crate::data_structures::coverage_data::test_data::coverage_data;
+    use crate::data_structures::FacetCoverage;
+
+    #[test]
+    fn test_bedgraph() {
+        let mut data = coverage_data();
+        data.significant_observations[0].effect_size = -2.0;
+        data.nonsignificant_observations[0].effect_size = f32::NAN;
+        let all = data.filter(&Default::default());
+
+        let mut output = vec![];
+        data.write_bedgraph(
+            &mut output,
+            &all,
+            FacetCoverage::Target,
+            BucketStatistic::MaxEffectSize,
+            Some("targets"),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "track type=bedGraph name=\"targets\"\nchr2\t4000000\t6000000\t-2\n"
+        );
+
+        let mut output = vec![];
+        data.write_bedgraph(
+            &mut output,
+            &all,
+            FacetCoverage::Source,
+            BucketStatistic::Count,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "chr1\t0\t2000000\t1\nchr1\t2000000\t4000000\t1\n\
+             chr2\t0\t2000000\t1\nchr2\t6000000\t8000000\t1\n"
+        );
+    }
+}
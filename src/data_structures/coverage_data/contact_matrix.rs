@@ -0,0 +1,184 @@
+use roaring::RoaringBitmap;
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::data_structures::{BucketLoc, CoverageData};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ContactCell {
+    pub count: u32,
+    pub max_neg_log_significance: f64,
+}
+
+// Source bin x target bin counts. Each bin merges `zoom` consecutive buckets, so a bin covers
+// `bin_size` bases of its chromosome.
+#[derive(Clone, Debug)]
+pub struct ContactMatrix {
+    pub zoom: u32,
+    pub bin_size: u32,
+    // The number of bins of each chromosome, by chromosome index
+    pub chrom_bins: Vec<u32>,
+    pub cells: FxHashMap<(BucketLoc, BucketLoc), ContactCell>,
+}
+
+// The non-empty cells of a contact matrix in coordinate format, sorted by source then target bin
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CooMatrix {
+    pub sources: Vec<BucketLoc>,
+    pub targets: Vec<BucketLoc>,
+    pub counts: Vec<u32>,
+    pub max_neg_log_significance: Vec<f64>,
+}
+
+// Rows are source bins and columns are target bins. Empty cells have a count of 0 and a
+// max -log10 significance of 0.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DenseMatrix {
+    pub source_chrom: u8,
+    pub target_chrom: u8,
+    pub counts: Vec<Vec<u32>>,
+    pub max_neg_log_significance: Vec<Vec<f64>>,
+}
+
+impl ContactMatrix {
+    pub fn to_coo(&self) -> CooMatrix {
+        let mut cells: Vec<_> = self.cells.iter().collect();
+        cells.sort_by_key(|(bins, _)| **bins);
+
+        let mut coo = CooMatrix::default();
+        for ((source, target), cell) in cells {
+            coo.sources.push(*source);
+            coo.targets.push(*target);
+            coo.counts.push(cell.count);
+            coo.max_neg_log_significance
+                .push(cell.max_neg_log_significance);
+        }
+        coo
+    }
+
+    // Returns None if either chromosome isn't in the matrix
+    pub fn to_dense(&self, source_chrom: u8, target_chrom: u8) -> Option<DenseMatrix> {
+        let rows = *self.chrom_bins.get(source_chrom as usize)? as usize;
+        let columns = *self.chrom_bins.get(target_chrom as usize)? as usize;
+
+        let mut dense = DenseMatrix {
+            source_chrom,
+            target_chrom,
+            counts: vec![vec![0; columns]; rows],
+            max_neg_log_significance: vec![vec![0.0; columns]; rows],
+        };
+        for ((source, target), cell) in &self.cells {
+            if source.chrom != source_chrom || target.chrom != target_chrom {
+                continue;
+            }
+            let (row, column) = (source.idx as usize, target.idx as usize);
+            if row < rows && column < columns {
+                dense.counts[row][column] = cell.count;
+                dense.max_neg_log_significance[row][column] = cell.max_neg_log_significance;
+            }
+        }
+        Some(dense)
+    }
+}
+
+impl CoverageData {
+    // Counts the observations at the given positions by source and target bin. Observations
+    // without a target, or whose source or target isn't in a bucket, aren't counted. A zoom
+    // of 1 uses the buckets as bins. NaN significances are ignored, so a cell whose observations
+    // all lack a significance has a max of 0, like an empty cell of a dense matrix.
+    pub fn contact_matrix(&self, positions: &RoaringBitmap, zoom: u32) -> ContactMatrix {
+        let zoom = zoom.max(1);
+        let bin_size = self.bucket_size.saturating_mul(zoom);
+        let chrom_bins = self
+            .chrom_lengths
+            .iter()
+            .map(|length| (*length as u64).div_ceil(bin_size as u64) as u32)
+            .collect();
+        let bin = |bucket: &BucketLoc| BucketLoc {
+            chrom: bucket.chrom,
+            idx: bucket.idx / zoom,
+        };
+
+        let mut cells: FxHashMap<(BucketLoc, BucketLoc), ContactCell> = FxHashMap::default();
+        for position in positions {
+            let observation = match self.observation(position) {
+                Some(observation) => observation,
+                None => continue,
+            };
+            let target_bucket = observation
+                .target_id
+                .and_then(|target_id| self.feature_buckets.get(&target_id));
+            let (source, target) = match (
+                self.feature_buckets.get(&observation.source_id),
+                target_bucket,
+            ) {
+                (Some(source), Some(target)) => (bin(source), bin(target)),
+                _ => continue,
+            };
+
+            let cell = cells.entry((source, target)).or_insert(ContactCell {
+                count: 0,
+                max_neg_log_significance: 0.0,
+            });
+            cell.count += 1;
+            cell.max_neg_log_significance = cell
+                .max_neg_log_significance
+                .max(observation.neg_log_significance);
+        }
+
+        ContactMatrix {
+            zoom,
+            bin_size,
+            chrom_bins,
+            cells,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data_structures::coverage_data::test_data::coverage_data;
+    use crate::data_structures::BucketLoc;
+
+    #[test]
+    fn test_contact_matrix_zoom() {
+        let mut data = coverage_data();
+        // Observations 0 and 1 share a cell at zoom 2; observation 2 has no significance
+        data.significant_observations[0].neg_log_significance = 3.0;
+        data.significant_observations[1].neg_log_significance = 5.0;
+        data.significant_observations[1].target_id = Some(100);
+        data.nonsignificant_observations[0].neg_log_significance = f64::NAN;
+        let all = data.filter(&Default::default());
+
+        let matrix = data.contact_matrix(&all, 2);
+        assert_eq!(matrix.chrom_bins, [3, 2]);
+        let coo = matrix.to_coo();
+        assert_eq!(
+            coo.sources,
+            [
+                BucketLoc { chrom: 0, idx: 0 },
+                BucketLoc { chrom: 1, idx: 0 }
+            ]
+        );
+        assert_eq!(
+            coo.targets,
+            [
+                BucketLoc { chrom: 1, idx: 1 },
+                BucketLoc { chrom: 0, idx: 2 }
+            ]
+        );
+
+        assert_eq!(coo.counts, [2, 1]);
+        assert_eq!(coo.max_neg_log_significance, [5.0, 0.0]);
+
+        let dense = matrix.to_dense(0, 1).unwrap();
+        assert_eq!(dense.counts, [[0, 2], [0, 0], [0, 0]]);
+        assert_eq!(
+            dense.max_neg_log_significance,
+            [[0.0, 5.0], [0.0, 0.0], [0.0, 0.0]]
+        );
+        let dense = matrix.to_dense(1, 0).unwrap();
+        assert_eq!(dense.counts, [[0, 0, 1], [0, 0, 0]]);
+        assert_eq!(dense.max_neg_log_significance, [[0.0; 3]; 2]);
+    }
+}
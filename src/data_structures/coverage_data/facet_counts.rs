@@ -0,0 +1,82 @@
+use roaring::RoaringBitmap;
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::data_structures::{CoverageData, DbID, FacetType, Filter};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FacetValueCount {
+    pub observations: u64,
+    pub sources: u64,
+    pub targets: u64,
+}
+
+impl CoverageData {
+    pub fn count_features(&self, positions: &RoaringBitmap) -> FacetValueCount {
+        let features = self.feature_data(positions);
+        FacetValueCount {
+            observations: positions.len(),
+            sources: features.sources.len(),
+            targets: features.targets.len(),
+        }
+    }
+
+    // For every categorical facet value, count what would match if that value were the one
+    // selected in its facet. The selections made in the other facets still apply, so
+    // toggling values within a facet doesn't change that facet's own counts. The counts of
+    // a parent value include the observations of its descendants.
+    pub fn facet_value_counts(&self, filter: &Filter) -> FxHashMap<DbID, FacetValueCount> {
+        let index = self.index();
+        let selected = self.selected_values_by_facet(filter);
+
+        let mut counts = FxHashMap::default();
+        for facet in &self.facets {
+            if facet.facet_type != FacetType::Categorical {
+                continue;
+            }
+            let values = match &facet.values {
+                Some(values) => values,
+                None => continue,
+            };
+
+            let mut other_facet_matches = index.all();
+            for (facet_id, value_ids) in &selected {
+                if *facet_id != Some(facet.id) {
+                    other_facet_matches &= index.with_any(value_ids);
+                }
+            }
+            self.retain_numeric_matches(filter, &mut other_facet_matches);
+
+            for value_id in values.keys() {
+                let matches =
+                    &other_facet_matches & index.with_any(&facet.value_and_descendants(*value_id));
+                counts.insert(*value_id, self.count_features(&matches));
+            }
+        }
+
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_hash::FxHashSet;
+
+    use crate::data_structures::coverage_data::test_data::coverage_data;
+    use crate::data_structures::Filter;
+
+    #[test]
+    fn test_counts_ignore_own_facet_selection() {
+        let data = coverage_data();
+        let counts = data.facet_value_counts(&Filter::new(FxHashSet::from_iter([10, 21])));
+
+        // Facet 1 is only restricted by the selection of 21 in facet 2
+        assert_eq!(counts[&10].observations, 1);
+        assert_eq!(counts[&11].observations, 0);
+        assert_eq!(counts[&12].observations, 1);
+        // Facet 2 is only restricted by the selection of 10 in facet 1
+        assert_eq!(counts[&20].observations, 1);
+        assert_eq!(counts[&21].observations, 1);
+        assert_eq!(counts[&21].sources, 1);
+    }
+}
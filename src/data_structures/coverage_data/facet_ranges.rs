@@ -0,0 +1,217 @@
+use crate::data_structures::{
+    CoverageData, DbID, Facet, FacetRange, FacetRange64, FacetType, NumericField, WellKnownFacet,
+};
+
+// A numeric facet whose stored range doesn't include every observed value
+#[derive(Clone, Debug)]
+pub struct FacetRangeMismatch {
+    pub facet_id: DbID,
+    pub facet_name: String,
+    pub stored: FacetRange64,
+    pub observed: FacetRange64,
+}
+
+// The smallest f32 range containing the range. Rounding to the nearest f32 could cut off the
+// smallest or largest value of an f64 column.
+fn outer_f32_range(range: FacetRange64) -> FacetRange {
+    let (mut min, mut max) = (range.0 as f32, range.1 as f32);
+    if min as f64 > range.0 {
+        min = min.next_down();
+    }
+    if (max as f64) < range.1 {
+        max = max.next_up();
+    }
+    FacetRange(min, max)
+}
+
+impl CoverageData {
+    // The smallest and largest values of the field, ignoring missing values
+    pub fn observed_range(&self, field: NumericField) -> Option<FacetRange64> {
+        (0..self.observation_count())
+            .filter_map(|position| self.numeric_value(position, field))
+            .fold(None, |range, value| match range {
+                None => Some(FacetRange64(value, value)),
+                Some(FacetRange64(min, max)) => Some(FacetRange64(min.min(value), max.max(value))),
+            })
+    }
+
+    pub fn observed_effect_size_range(&self) -> Option<FacetRange> {
+        self.observed_range(NumericField::EffectSize)
+            .map(|range| FacetRange(range.0 as f32, range.1 as f32))
+    }
+
+    // Range of the raw p values
+    pub fn observed_p_value_range(&self) -> Option<FacetRange64> {
+        self.observed_range(NumericField::Significance)
+    }
+
+    // Range of the -log10 significance values
+    pub fn observed_significance_range(&self) -> Option<FacetRange64> {
+        self.observed_range(NumericField::NegLogSignificance)
+    }
+
+    // The field holding the values of a numeric facet, if there is one
+    fn facet_field(&self, facet: &Facet) -> Option<NumericField> {
+        match facet.well_known() {
+            Some(WellKnownFacet::EffectSize) => Some(NumericField::EffectSize),
+            Some(WellKnownFacet::RawPValue) => Some(NumericField::Significance),
+            Some(WellKnownFacet::Significance) => Some(NumericField::NegLogSignificance),
+            _ => self
+                .numeric_column(facet.id)
+                .map(|column| NumericField::Facet(column.facet_id)),
+        }
+    }
+
+    // Replaces the stored ranges of the effect size, raw p value, and significance facets, and
+    // of the facets with a numeric column, with the ranges of the observations. Facets without
+    // any observed values keep their stored range.
+    pub fn update_facet_ranges(&mut self) {
+        let observed_ranges: Vec<Option<FacetRange64>> = self
+            .facets
+            .iter()
+            .map(|facet| {
+                self.facet_field(facet)
+                    .and_then(|field| self.observed_range(field))
+            })
+            .collect();
+
+        for (facet, observed) in self.facets.iter_mut().zip(observed_ranges) {
+            let observed = match observed {
+                Some(observed) => observed,
+                None => continue,
+            };
+            // Legacy numeric facets are all "FacetType.NUMERIC", so prefer the well-known type
+            let facet_type = facet
+                .well_known()
+                .map_or(facet.facet_type, |well_known| well_known.facet_type());
+            if facet_type == FacetType::Numeric64 {
+                facet.range64 = Some(observed);
+            } else {
+                facet.range = Some(outer_f32_range(observed));
+            }
+        }
+    }
+
+    // Lists the numeric facets with a stored range that doesn't bound the observations
+    pub fn validate_facet_ranges(&self) -> Vec<FacetRangeMismatch> {
+        let mut mismatches = vec![];
+        for facet in &self.facets {
+            let field = match self.facet_field(facet) {
+                Some(field) => field,
+                None => continue,
+            };
+
+            if let (Some(stored), Some(observed)) =
+                (self.numeric_field_range(field), self.observed_range(field))
+            {
+                if stored.0 > observed.0 || stored.1 < observed.1 {
+                    mismatches.push(FacetRangeMismatch {
+                        facet_id: facet.id,
+                        facet_name: facet.name.clone(),
+                        stored,
+                        observed,
+                    });
+                }
+            }
+        }
+        mismatches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data_structures::coverage_data::test_data::{categorical_facet, coverage_data};
+    use crate::data_structures::{
+        CoverageData, DbID, Facet, FacetRange, FacetRange64, FacetType, WellKnownFacet,
+    };
+
+    fn numeric_facet(id: DbID, name: &str, facet_type: FacetType) -> Facet {
+        let mut facet = categorical_facet(id, &[]);
+        facet.name = name.to_string();
+        facet.facet_type = facet_type;
+        facet.values = None;
+        facet
+    }
+
+    // Effect sizes are NaN, -2, 0.5 and 3, every significance is NaN, and the "Base Mean"
+    // column is 1, NaN, 2 and NaN
+    fn ranged_data() -> CoverageData {
+        let mut data = coverage_data();
+        for (observation, effect_size) in data
+            .significant_observations
+            .iter_mut()
+            .chain(data.nonsignificant_observations.iter_mut())
+            .zip([f32::NAN, -2.0, 0.5, 3.0])
+        {
+            observation.effect_size = effect_size;
+            observation.neg_log_significance = f64::NAN;
+        }
+
+        let mut effect_size =
+            numeric_facet(3, WellKnownFacet::EffectSize.name(), FacetType::Numeric);
+        effect_size.range = Some(FacetRange(-1.0, 1.0));
+        let mut significance =
+            numeric_facet(4, WellKnownFacet::Significance.name(), FacetType::Numeric64);
+        significance.range64 = Some(FacetRange64(0.0, 10.0));
+        data.facets.extend([effect_size, significance]);
+        data.add_numeric_column(
+            numeric_facet(5, "Base Mean", FacetType::Numeric64),
+            vec![1.0, f64::NAN, 2.0, f64::NAN],
+        )
+        .unwrap();
+        data
+    }
+
+    #[test]
+    fn test_validate_facet_ranges() {
+        let data = ranged_data();
+
+        // The stored effect size range is too narrow. Significance has no observed values and
+        // "Base Mean" has no stored range, so neither can be checked.
+        let mismatches = data.validate_facet_ranges();
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].facet_id, 3);
+        assert_eq!(
+            (mismatches[0].stored.0, mismatches[0].stored.1),
+            (-1.0, 1.0)
+        );
+        assert_eq!(
+            (mismatches[0].observed.0, mismatches[0].observed.1),
+            (-2.0, 3.0)
+        );
+    }
+
+    #[test]
+    fn test_update_facet_ranges_ignores_nan() {
+        let mut data = ranged_data();
+        data.update_facet_ranges();
+
+        let effect_size = data.facets[2].range.unwrap();
+        assert_eq!((effect_size.0, effect_size.1), (-2.0, 3.0));
+        // Significance keeps its stored range
+        let significance = data.facets[3].range64.unwrap();
+        assert_eq!((significance.0, significance.1), (0.0, 10.0));
+        let base_mean = data.facets[4].range64.unwrap();
+        assert_eq!((base_mean.0, base_mean.1), (1.0, 2.0));
+
+        assert!(data.validate_facet_ranges().is_empty());
+    }
+
+    #[test]
+    fn test_update_f32_facet_range() {
+        // Neither value is an f32, and both round towards the middle of the range
+        let (min, max) = (0.1, 0.7);
+        assert!(min as f32 as f64 > min && (max as f32 as f64) < max);
+        let mut data = coverage_data();
+        data.add_numeric_column(
+            numeric_facet(3, "Score", FacetType::Numeric),
+            vec![min, 0.5, max, f64::NAN],
+        )
+        .unwrap();
+        data.update_facet_ranges();
+
+        let range = data.facets[2].range.unwrap();
+        assert!(range.0 as f64 <= min && range.1 as f64 >= max);
+        assert!(data.validate_facet_ranges().is_empty());
+    }
+}
@@ -0,0 +1,106 @@
+use roaring::RoaringTreemap;
+use serde::{Deserialize, Serialize};
+
+use crate::data_structures::{CoverageData, ExperimentFeatureData, FacetCoverage};
+
+// The number of features in each bucket of a chromosome, by bucket index
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChromosomeFeatureDensity {
+    pub chrom: u8,
+    pub sources: Vec<u32>,
+    pub targets: Vec<u32>,
+}
+
+// The features the experiment could have detected anything at. Untestable features aren't, but
+// only data with feature statuses says which features those are.
+fn detectable(features: &ExperimentFeatureData, coverage: FacetCoverage) -> &RoaringTreemap {
+    let status = features.status(coverage);
+    if !status.is_empty() {
+        return &status.tested;
+    }
+    match coverage {
+        FacetCoverage::Source => &features.sources,
+        FacetCoverage::Target => &features.targets,
+    }
+}
+
+impl CoverageData {
+    fn count_by_bucket(&self, features: &RoaringTreemap, counts: &mut [Vec<u32>]) {
+        for feature_id in features {
+            let bucket = match self.feature_buckets.get(&feature_id) {
+                Some(bucket) => bucket,
+                None => continue,
+            };
+            if let Some(count) = counts
+                .get_mut(bucket.chrom as usize)
+                .and_then(|chrom_counts| chrom_counts.get_mut(bucket.idx as usize))
+            {
+                *count += 1;
+            }
+        }
+    }
+
+    // Where an experiment's tested sources and targets are, e.g. to show where the experiment
+    // could have detected anything. Features that aren't in a bucket aren't counted.
+    pub fn feature_density(
+        &self,
+        features: &ExperimentFeatureData,
+    ) -> Vec<ChromosomeFeatureDensity> {
+        let bucket_size = self.bucket_size.max(1) as usize;
+        let empty_counts: Vec<Vec<u32>> = self
+            .chrom_lengths
+            .iter()
+            .map(|length| vec![0; length.div_ceil(bucket_size)])
+            .collect();
+
+        let mut sources = empty_counts.clone();
+        self.count_by_bucket(detectable(features, FacetCoverage::Source), &mut sources);
+        let mut targets = empty_counts;
+        self.count_by_bucket(detectable(features, FacetCoverage::Target), &mut targets);
+
+        sources
+            .into_iter()
+            .zip(targets)
+            .enumerate()
+            .map(|(chrom, (sources, targets))| ChromosomeFeatureDensity {
+                chrom: chrom as u8,
+                sources,
+                targets,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use roaring::{RoaringBitmap, RoaringTreemap};
+
+    use crate::data_structures::coverage_data::test_data::coverage_data;
+    use crate::data_structures::ExperimentFeatureData;
+
+    #[test]
+    fn test_feature_density() {
+        let data = coverage_data();
+        let features = data.feature_data(&data.filter(&Default::default()));
+
+        let density = data.feature_density(&features);
+        assert_eq!(density[0].sources, [1, 1, 0, 0, 0]);
+        assert_eq!(density[0].targets, [0, 0, 0, 0, 1]);
+        assert_eq!(density[1].sources, [1, 0, 0, 1]);
+        assert_eq!(density[1].targets, [0, 0, 1, 0]);
+
+        // Source 3 is untestable, so it's one of the sources but isn't counted
+        let positions = RoaringBitmap::from_iter([0, 1, 2]);
+        let untestable = RoaringTreemap::from_iter([3]);
+        let features =
+            data.feature_data_with_status(&positions, &untestable, &RoaringTreemap::new());
+        assert!(features.sources.contains(3));
+        let density = data.feature_density(&features);
+        assert_eq!(density[1].sources, [1, 0, 0, 0]);
+
+        // Without statuses every source counts
+        let features = ExperimentFeatureData::new(features.sources, features.targets);
+        let density = data.feature_density(&features);
+        assert_eq!(density[1].sources, [1, 0, 0, 1]);
+    }
+}
@@ -0,0 +1,142 @@
+use std::io::{self, Write};
+
+use roaring::RoaringTreemap;
+
+use crate::data_structures::{
+    ChromosomeData, DbID, ExperimentFeatureData, FacetCoverage, FeatureCoordinates, FeatureLocation,
+};
+
+impl ExperimentFeatureData {
+    pub fn features(&self, coverage: FacetCoverage) -> &RoaringTreemap {
+        match coverage {
+            FacetCoverage::Source => &self.sources,
+            FacetCoverage::Target => &self.targets,
+        }
+    }
+
+    // The sources or targets that have coordinates, in genome order
+    fn located_features<'a>(
+        &self,
+        coverage: FacetCoverage,
+        coordinates: &'a FeatureCoordinates,
+    ) -> Vec<(DbID, &'a FeatureLocation)> {
+        let mut features: Vec<_> = self
+            .features(coverage)
+            .iter()
+            .filter_map(|feature_id| Some((feature_id, coordinates.get(feature_id)?)))
+            .collect();
+        features.sort_by_key(|(feature_id, location)| {
+            (location.chrom, location.start, location.end, *feature_id)
+        });
+        features
+    }
+
+    // Writes the sources or targets as BED, named by their name or else their ID. Features
+    // without coordinates on one of the chromosomes are left out.
+    pub fn write_bed<W: Write>(
+        &self,
+        writer: &mut W,
+        coverage: FacetCoverage,
+        coordinates: &FeatureCoordinates,
+        chromosomes: &[ChromosomeData],
+    ) -> io::Result<()> {
+        for (feature_id, location) in self.located_features(coverage, coordinates) {
+            let chrom = match chromosomes
+                .iter()
+                .find(|chrom| chrom.index == location.chrom)
+            {
+                Some(chrom) => &chrom.chrom,
+                None => continue,
+            };
+            match &location.name {
+                Some(name) => writeln!(
+                    writer,
+                    "{}\t{}\t{}\t{}",
+                    chrom, location.start, location.end, name
+                )?,
+                None => writeln!(
+                    writer,
+                    "{}\t{}\t{}\t{}",
+                    chrom, location.start, location.end, feature_id
+                )?,
+            }
+        }
+        Ok(())
+    }
+
+    // The distinct names (e.g. gene symbols) of the sources or targets, sorted. Features without
+    // a name are left out.
+    pub fn feature_names<'a>(
+        &self,
+        coverage: FacetCoverage,
+        coordinates: &'a FeatureCoordinates,
+    ) -> Vec<&'a str> {
+        let mut names: Vec<&str> = self
+            .features(coverage)
+            .iter()
+            .filter_map(|feature_id| coordinates.get(feature_id)?.name.as_deref())
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    // Writes `feature_names`, one per line
+    pub fn write_feature_names<W: Write>(
+        &self,
+        writer: &mut W,
+        coverage: FacetCoverage,
+        coordinates: &FeatureCoordinates,
+    ) -> io::Result<()> {
+        for name in self.feature_names(coverage, coordinates) {
+            writeln!(writer, "{}", name)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use roaring::RoaringTreemap;
+
+    use crate::data_structures::coverage_data::test_data::{coverage_data, feature_coordinates};
+    use crate::data_structures::{ExperimentFeatureData, FacetCoverage};
+
+    #[test]
+    fn test_feature_export() {
+        let data = coverage_data();
+        let mut coordinates = feature_coordinates();
+        // Two features of the same gene
+        let mut duplicate = coordinates.get(100).unwrap().clone();
+        duplicate.start += 1000;
+        duplicate.end += 1000;
+        coordinates.insert(102, duplicate);
+
+        let features = ExperimentFeatureData::new(
+            RoaringTreemap::from_iter([2, 0, 7]),
+            RoaringTreemap::from_iter([101, 100, 102]),
+        );
+
+        let mut bed = vec![];
+        features
+            .write_bed(
+                &mut bed,
+                FacetCoverage::Source,
+                &coordinates,
+                &data.chromosomes,
+            )
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(bed).unwrap(),
+            "chr1\t100\t200\t0\nchr2\t100\t200\t2\n"
+        );
+
+        assert_eq!(
+            features.feature_names(FacetCoverage::Target, &coordinates),
+            ["GENE100", "GENE101"]
+        );
+        assert!(features
+            .feature_names(FacetCoverage::Source, &coordinates)
+            .is_empty());
+    }
+}
@@ -0,0 +1,310 @@
+use roaring::{RoaringBitmap, RoaringTreemap};
+use serde::{Deserialize, Serialize};
+
+use crate::data_structures::{
+    CoverageData, DbID, ExperimentFeatureData, FacetCoverage, FeatureStatus,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FeatureSimilarity {
+    // |A ∩ B| / |A ∪ B|
+    Jaccard,
+    // |A ∩ B| / min(|A|, |B|)
+    Overlap,
+}
+
+impl CoverageData {
+    // The sources and targets of the observations at the given positions, e.g. the result of
+    // `CoverageData::filter`, with their statuses. None of them are untestable.
+    pub fn feature_data(&self, positions: &RoaringBitmap) -> ExperimentFeatureData {
+        self.feature_data_with_status(positions, &RoaringTreemap::new(), &RoaringTreemap::new())
+    }
+
+    // Features of the observations at the given positions are tested. Features of significant
+    // observations are also significant in the direction of the observation's effect size, so a
+    // feature with significant effects in both directions is in both sets. Which features
+    // couldn't be tested isn't part of the coverage data, so the caller supplies them; any of
+    // them that were tested anyway count as tested.
+    pub fn feature_data_with_status(
+        &self,
+        positions: &RoaringBitmap,
+        untestable_sources: &RoaringTreemap,
+        untestable_targets: &RoaringTreemap,
+    ) -> ExperimentFeatureData {
+        let mut source_status = FeatureStatus::default();
+        let mut target_status = FeatureStatus::default();
+        let significant_count = self.significant_observations.len() as u32;
+        for position in positions {
+            let observation = match self.observation(position) {
+                Some(observation) => observation,
+                None => continue,
+            };
+            let effect_size = if position < significant_count {
+                Some(observation.effect_size)
+            } else {
+                None
+            };
+            add_tested(&mut source_status, observation.source_id, effect_size);
+            if let Some(target_id) = observation.target_id {
+                add_tested(&mut target_status, target_id, effect_size);
+            }
+        }
+
+        source_status.untestable = untestable_sources - &source_status.tested;
+        target_status.untestable = untestable_targets - &target_status.tested;
+        ExperimentFeatureData::with_status(source_status, target_status)
+    }
+}
+
+// `effect_size` is None for nonsignificant observations. A significant observation with an
+// effect size of 0 or NaN only makes the feature tested.
+fn add_tested(status: &mut FeatureStatus, feature_id: DbID, effect_size: Option<f32>) {
+    status.tested.insert(feature_id);
+    match effect_size {
+        Some(effect_size) if effect_size > 0.0 => {
+            status.significant_positive.insert(feature_id);
+        }
+        Some(effect_size) if effect_size < 0.0 => {
+            status.significant_negative.insert(feature_id);
+        }
+        _ => (),
+    }
+}
+
+// Set operations apply to sources and targets separately. The results don't have feature
+// statuses.
+impl ExperimentFeatureData {
+    pub fn union(&self, other: &ExperimentFeatureData) -> ExperimentFeatureData {
+        ExperimentFeatureData::new(
+            &self.sources | &other.sources,
+            &self.targets | &other.targets,
+        )
+    }
+
+    pub fn intersection(&self, other: &ExperimentFeatureData) -> ExperimentFeatureData {
+        ExperimentFeatureData::new(
+            &self.sources & &other.sources,
+            &self.targets & &other.targets,
+        )
+    }
+
+    pub fn difference(&self, other: &ExperimentFeatureData) -> ExperimentFeatureData {
+        ExperimentFeatureData::new(
+            &self.sources - &other.sources,
+            &self.targets - &other.targets,
+        )
+    }
+
+    pub fn symmetric_difference(&self, other: &ExperimentFeatureData) -> ExperimentFeatureData {
+        ExperimentFeatureData::new(
+            &self.sources ^ &other.sources,
+            &self.targets ^ &other.targets,
+        )
+    }
+
+    pub fn union_all<'a>(
+        experiments: impl IntoIterator<Item = &'a ExperimentFeatureData>,
+    ) -> ExperimentFeatureData {
+        experiments
+            .into_iter()
+            .fold(ExperimentFeatureData::default(), |union, experiment| {
+                union.union(experiment)
+            })
+    }
+
+    // The features in every experiment. Empty if there aren't any experiments.
+    pub fn intersection_all<'a>(
+        experiments: impl IntoIterator<Item = &'a ExperimentFeatureData>,
+    ) -> ExperimentFeatureData {
+        let mut experiments = experiments.into_iter();
+        match experiments.next() {
+            Some(first) => experiments.fold(first.clone(), |intersection, experiment| {
+                intersection.intersection(experiment)
+            }),
+            None => ExperimentFeatureData::default(),
+        }
+    }
+
+    // The features of this experiment that aren't in any of the others
+    pub fn difference_all<'a>(
+        &self,
+        others: impl IntoIterator<Item = &'a ExperimentFeatureData>,
+    ) -> ExperimentFeatureData {
+        self.difference(&ExperimentFeatureData::union_all(others))
+    }
+
+    // The features in an odd number of the experiments
+    pub fn symmetric_difference_all<'a>(
+        experiments: impl IntoIterator<Item = &'a ExperimentFeatureData>,
+    ) -> ExperimentFeatureData {
+        experiments.into_iter().fold(
+            ExperimentFeatureData::default(),
+            |difference, experiment| difference.symmetric_difference(experiment),
+        )
+    }
+
+    // The similarity of the experiments' sources or targets. 0 if either set is empty.
+    pub fn similarity(
+        &self,
+        other: &ExperimentFeatureData,
+        coverage: FacetCoverage,
+        similarity: FeatureSimilarity,
+    ) -> f64 {
+        let (a, b) = (self.features(coverage), other.features(coverage));
+        let denominator = match similarity {
+            FeatureSimilarity::Jaccard => a.union_len(b),
+            FeatureSimilarity::Overlap => a.len().min(b.len()),
+        };
+        if denominator == 0 {
+            return 0.0;
+        }
+        a.intersection_len(b) as f64 / denominator as f64
+    }
+
+    pub fn jaccard(&self, other: &ExperimentFeatureData, coverage: FacetCoverage) -> f64 {
+        self.similarity(other, coverage, FeatureSimilarity::Jaccard)
+    }
+
+    pub fn overlap_coefficient(
+        &self,
+        other: &ExperimentFeatureData,
+        coverage: FacetCoverage,
+    ) -> f64 {
+        self.similarity(other, coverage, FeatureSimilarity::Overlap)
+    }
+
+    // The pairwise similarities of the experiments, in the given order. The matrix is symmetric.
+    pub fn similarity_matrix(
+        experiments: &[ExperimentFeatureData],
+        coverage: FacetCoverage,
+        similarity: FeatureSimilarity,
+    ) -> Vec<Vec<f64>> {
+        let mut matrix = vec![vec![0.0; experiments.len()]; experiments.len()];
+        for (i, a) in experiments.iter().enumerate() {
+            for (j, b) in experiments.iter().enumerate().skip(i) {
+                let value = a.similarity(b, coverage, similarity);
+                matrix[i][j] = value;
+                matrix[j][i] = value;
+            }
+        }
+        matrix
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use roaring::RoaringTreemap;
+    use rustc_hash::FxHashSet;
+
+    use super::FeatureSimilarity;
+    use crate::data_structures::coverage_data::test_data::coverage_data;
+    use crate::data_structures::{ExperimentFeatureData, FacetCoverage, Filter};
+
+    fn experiment(sources: &[u64], targets: &[u64]) -> ExperimentFeatureData {
+        ExperimentFeatureData::new(
+            RoaringTreemap::from_iter(sources.iter().copied()),
+            RoaringTreemap::from_iter(targets.iter().copied()),
+        )
+    }
+
+    #[test]
+    fn test_feature_data_of_filter() {
+        let data = coverage_data();
+        let positions = data.filter(&Filter::new(FxHashSet::from_iter([10])));
+
+        let features = data.feature_data(&positions);
+        assert_eq!(features.sources, RoaringTreemap::from_iter([0, 3]));
+        assert_eq!(features.targets, RoaringTreemap::from_iter([100]));
+    }
+
+    #[test]
+    fn test_feature_data_with_status() {
+        let mut data = coverage_data();
+        // Observations 0 and 1 are significant, with effects in opposite directions. The
+        // nonsignificant observation 3 has a large effect, which doesn't matter.
+        data.significant_observations[0].effect_size = 1.5;
+        data.significant_observations[1].effect_size = -0.5;
+        data.significant_observations[1].target_id = Some(100);
+        data.nonsignificant_observations[1].effect_size = 4.0;
+        let all = data.filter(&Filter::default());
+
+        let features = data.feature_data_with_status(
+            &all,
+            &RoaringTreemap::from_iter([3, 7]),
+            &RoaringTreemap::from_iter([102]),
+        );
+        let sources = &features.source_status;
+        assert_eq!(sources.tested, RoaringTreemap::from_iter([0, 1, 2, 3]));
+        assert_eq!(sources.significant_positive, RoaringTreemap::from_iter([0]));
+        assert_eq!(sources.significant_negative, RoaringTreemap::from_iter([1]));
+        // Source 3 was tested, so it isn't untestable
+        assert_eq!(sources.untestable, RoaringTreemap::from_iter([7]));
+        assert_eq!(features.sources, RoaringTreemap::from_iter([0, 1, 2, 3, 7]));
+
+        // Target 100 has significant effects in both directions
+        let targets = &features.target_status;
+        assert_eq!(targets.tested, RoaringTreemap::from_iter([100, 101]));
+        assert_eq!(
+            targets.significant_positive,
+            RoaringTreemap::from_iter([100])
+        );
+        assert_eq!(
+            targets.significant_negative,
+            RoaringTreemap::from_iter([100])
+        );
+        assert_eq!(targets.not_significant(), RoaringTreemap::from_iter([101]));
+        assert_eq!(targets.untestable, RoaringTreemap::from_iter([102]));
+
+        for status in [sources, targets] {
+            assert!(status.significant().is_subset(&status.tested));
+            assert!(status.untestable.is_disjoint(&status.tested));
+        }
+
+        // Without untestable features, a filter only narrows the statuses
+        let features = data.feature_data(&data.filter(&Filter::new(FxHashSet::from_iter([11]))));
+        assert_eq!(features.sources, RoaringTreemap::from_iter([1]));
+        assert_eq!(
+            features.source_status.significant_negative,
+            RoaringTreemap::from_iter([1])
+        );
+        assert!(features.source_status.untestable.is_empty());
+    }
+
+    #[test]
+    fn test_feature_set_algebra() {
+        let experiments = [
+            experiment(&[1, 2, 3], &[10]),
+            experiment(&[2, 3, 4], &[10, 11]),
+            experiment(&[3, 5], &[]),
+        ];
+
+        let union = ExperimentFeatureData::union_all(&experiments);
+        assert_eq!(union.sources, RoaringTreemap::from_iter([1, 2, 3, 4, 5]));
+        let intersection = ExperimentFeatureData::intersection_all(&experiments);
+        assert_eq!(intersection.sources, RoaringTreemap::from_iter([3]));
+        assert!(intersection.targets.is_empty());
+        let difference = experiments[0].difference_all(&experiments[1..]);
+        assert_eq!(difference.sources, RoaringTreemap::from_iter([1]));
+        let symmetric_difference = ExperimentFeatureData::symmetric_difference_all(&experiments);
+        assert_eq!(
+            symmetric_difference.sources,
+            RoaringTreemap::from_iter([1, 3, 4, 5])
+        );
+
+        assert_eq!(
+            experiments[0].jaccard(&experiments[1], FacetCoverage::Source),
+            0.5
+        );
+        assert_eq!(
+            experiments[0].overlap_coefficient(&experiments[1], FacetCoverage::Target),
+            1.0
+        );
+
+        let matrix = ExperimentFeatureData::similarity_matrix(
+            &experiments,
+            FacetCoverage::Target,
+            FeatureSimilarity::Jaccard,
+        );
+        assert_eq!(matrix, [[1.0, 0.5, 0.0], [0.5, 1.0, 0.0], [0.0, 0.0, 0.0]]);
+    }
+}
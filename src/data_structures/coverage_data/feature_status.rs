@@ -0,0 +1,174 @@
+use std::fmt;
+
+use roaring::RoaringTreemap;
+use serde::de::{self, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{self, SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
+
+// How the features of one kind (sources or targets) fared in an experiment. Significant
+// features are also tested; untestable features are in none of the other sets.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FeatureStatus {
+    pub tested: RoaringTreemap,
+    pub significant_positive: RoaringTreemap,
+    pub significant_negative: RoaringTreemap,
+    pub untestable: RoaringTreemap,
+}
+
+const FEATURE_STATUS_FIELD_TESTED: &str = "tested";
+const FEATURE_STATUS_FIELD_SIGNIFICANT_POSITIVE: &str = "significant_positive";
+const FEATURE_STATUS_FIELD_SIGNIFICANT_NEGATIVE: &str = "significant_negative";
+const FEATURE_STATUS_FIELD_UNTESTABLE: &str = "untestable";
+
+impl FeatureStatus {
+    // Every feature the experiment included, whether or not it could be tested
+    pub fn all(&self) -> RoaringTreemap {
+        &self.tested | &self.untestable
+    }
+
+    pub fn significant(&self) -> RoaringTreemap {
+        &self.significant_positive | &self.significant_negative
+    }
+
+    // Tested features that weren't significant
+    pub fn not_significant(&self) -> RoaringTreemap {
+        &self.tested - &self.significant()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tested.is_empty() && self.untestable.is_empty()
+    }
+}
+
+pub(crate) fn treemap_bytes<E: ser::Error>(treemap: &RoaringTreemap) -> Result<Vec<u8>, E> {
+    let mut bytes = vec![];
+    treemap
+        .serialize_into(&mut bytes)
+        .map_err(ser::Error::custom)?;
+    Ok(bytes)
+}
+
+pub(crate) fn treemap_from_bytes<E: de::Error>(bytes: &[u8]) -> Result<RoaringTreemap, E> {
+    RoaringTreemap::deserialize_from(bytes).map_err(de::Error::custom)
+}
+
+impl Serialize for FeatureStatus {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("FeatureStatus", 4)?;
+        state.serialize_field(FEATURE_STATUS_FIELD_TESTED, &treemap_bytes(&self.tested)?)?;
+        state.serialize_field(
+            FEATURE_STATUS_FIELD_SIGNIFICANT_POSITIVE,
+            &treemap_bytes(&self.significant_positive)?,
+        )?;
+        state.serialize_field(
+            FEATURE_STATUS_FIELD_SIGNIFICANT_NEGATIVE,
+            &treemap_bytes(&self.significant_negative)?,
+        )?;
+        state.serialize_field(
+            FEATURE_STATUS_FIELD_UNTESTABLE,
+            &treemap_bytes(&self.untestable)?,
+        )?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for FeatureStatus {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(field_identifier, rename_all = "snake_case")]
+        enum Field {
+            Tested,
+            SignificantPositive,
+            SignificantNegative,
+            Untestable,
+        }
+
+        struct FeatureStatusVisitor;
+
+        impl<'de> Visitor<'de> for FeatureStatusVisitor {
+            type Value = FeatureStatus;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("struct FeatureStatus")
+            }
+
+            fn visit_seq<V>(self, mut seq: V) -> Result<FeatureStatus, V::Error>
+            where
+                V: SeqAccess<'de>,
+            {
+                let mut next = |i| -> Result<RoaringTreemap, V::Error> {
+                    let bytes: Vec<u8> = seq
+                        .next_element()?
+                        .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+                    treemap_from_bytes(&bytes)
+                };
+
+                Ok(FeatureStatus {
+                    tested: next(0)?,
+                    significant_positive: next(1)?,
+                    significant_negative: next(2)?,
+                    untestable: next(3)?,
+                })
+            }
+
+            fn visit_map<V>(self, mut map: V) -> Result<FeatureStatus, V::Error>
+            where
+                V: MapAccess<'de>,
+            {
+                let mut tested: Option<Vec<u8>> = None;
+                let mut significant_positive: Option<Vec<u8>> = None;
+                let mut significant_negative: Option<Vec<u8>> = None;
+                let mut untestable: Option<Vec<u8>> = None;
+                while let Some(key) = map.next_key()? {
+                    let (field, name) = match key {
+                        Field::Tested => (&mut tested, FEATURE_STATUS_FIELD_TESTED),
+                        Field::SignificantPositive => (
+                            &mut significant_positive,
+                            FEATURE_STATUS_FIELD_SIGNIFICANT_POSITIVE,
+                        ),
+                        Field::SignificantNegative => (
+                            &mut significant_negative,
+                            FEATURE_STATUS_FIELD_SIGNIFICANT_NEGATIVE,
+                        ),
+                        Field::Untestable => (&mut untestable, FEATURE_STATUS_FIELD_UNTESTABLE),
+                    };
+                    if field.is_some() {
+                        return Err(de::Error::duplicate_field(name));
+                    }
+                    *field = Some(map.next_value()?);
+                }
+
+                let treemap = |bytes: Option<Vec<u8>>, name| match bytes {
+                    Some(bytes) => treemap_from_bytes(&bytes),
+                    None => Err(de::Error::missing_field(name)),
+                };
+                Ok(FeatureStatus {
+                    tested: treemap(tested, FEATURE_STATUS_FIELD_TESTED)?,
+                    significant_positive: treemap(
+                        significant_positive,
+                        FEATURE_STATUS_FIELD_SIGNIFICANT_POSITIVE,
+                    )?,
+                    significant_negative: treemap(
+                        significant_negative,
+                        FEATURE_STATUS_FIELD_SIGNIFICANT_NEGATIVE,
+                    )?,
+                    untestable: treemap(untestable, FEATURE_STATUS_FIELD_UNTESTABLE)?,
+                })
+            }
+        }
+
+        const FIELDS: &[&str] = &[
+            FEATURE_STATUS_FIELD_TESTED,
+            FEATURE_STATUS_FIELD_SIGNIFICANT_POSITIVE,
+            FEATURE_STATUS_FIELD_SIGNIFICANT_NEGATIVE,
+            FEATURE_STATUS_FIELD_UNTESTABLE,
+        ];
+        deserializer.deserialize_struct("FeatureStatus", FIELDS, FeatureStatusVisitor)
+    }
+}
@@ -0,0 +1,276 @@
+use std::borrow::Cow;
+
+use roaring::RoaringBitmap;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::data_structures::{
+    CoverageData, DbID, Facet, FacetIndex, FacetRange64, NumericField, ObservationData,
+};
+
+// Inclusive bounds on effect size and -log10 significance
+#[derive(Clone, Copy, Debug)]
+pub struct FilterIntervals {
+    pub effect: (f32, f32),
+    pub sig: (f64, f64),
+}
+
+impl FilterIntervals {
+    pub fn contains(&self, observation: &ObservationData) -> bool {
+        observation.effect_size >= self.effect.0
+            && observation.effect_size <= self.effect.1
+            && observation.neg_log_significance >= self.sig.0
+            && observation.neg_log_significance <= self.sig.1
+    }
+}
+
+// Selected facet values of the same facet are alternatives (OR), while each facet with at
+// least one selected value must be matched (AND). Facets without a selected value don't
+// restrict the results. `numeric_facets` holds inclusive bounds on the values of numeric
+// facets with a `NumericColumn`; observations without a value don't match.
+#[derive(Clone, Debug, Default)]
+pub struct Filter {
+    pub categorical_facets: FxHashSet<DbID>,
+    pub numeric_intervals: Option<FilterIntervals>,
+    pub numeric_facets: FxHashMap<DbID, FacetRange64>,
+}
+
+impl Filter {
+    pub fn new(categorical_facets: FxHashSet<DbID>) -> Self {
+        Filter {
+            categorical_facets,
+            numeric_intervals: None,
+            numeric_facets: FxHashMap::default(),
+        }
+    }
+
+    pub fn with_intervals(mut self, numeric_intervals: FilterIntervals) -> Self {
+        self.numeric_intervals = Some(numeric_intervals);
+        self
+    }
+
+    pub fn with_numeric_facet(mut self, facet_id: DbID, range: FacetRange64) -> Self {
+        self.numeric_facets.insert(facet_id, range);
+        self
+    }
+}
+
+impl CoverageData {
+    pub fn observation_count(&self) -> u32 {
+        (self.significant_observations.len() + self.nonsignificant_observations.len()) as u32
+    }
+
+    // Observation positions number the significant observations first, followed by the
+    // nonsignificant observations.
+    pub fn observation(&self, position: u32) -> Option<&ObservationData> {
+        let position = position as usize;
+        let sig_count = self.significant_observations.len();
+        if position < sig_count {
+            self.significant_observations.get(position)
+        } else {
+            self.nonsignificant_observations.get(position - sig_count)
+        }
+    }
+
+    pub fn observations(&self) -> impl Iterator<Item = &ObservationData> {
+        self.significant_observations
+            .iter()
+            .chain(self.nonsignificant_observations.iter())
+    }
+
+    pub fn significant_positions(&self) -> RoaringBitmap {
+        let mut positions = RoaringBitmap::new();
+        positions.insert_range(0..self.significant_observations.len() as u32);
+        positions
+    }
+
+    // The stored facet index isn't updated when observations change, so it has to be rebuilt
+    // (or removed) after changing them.
+    pub fn build_facet_index(&mut self) {
+        self.facet_index = Some(FacetIndex::build(self.observations()));
+    }
+
+    // Whether the stored facet index, if there is one, covers the observations: the same number
+    // of observations, no positions past the last one, and one entry for each facet value of
+    // each observation. This is cheaper than rebuilding the index, but doesn't notice an
+    // observation's facet value being swapped for another.
+    pub fn facet_index_is_valid(&self) -> bool {
+        let index = match &self.facet_index {
+            Some(index) => index,
+            None => return true,
+        };
+        if index.observation_count != self.observation_count() {
+            return false;
+        }
+
+        let mut entry_count = 0;
+        for positions in index.facet_values.values() {
+            if positions
+                .max()
+                .is_some_and(|position| position >= index.observation_count)
+            {
+                return false;
+            }
+            entry_count += positions.len();
+        }
+        // Repeated values of an observation only have one entry
+        let value_count: usize = self
+            .observations()
+            .map(|observation| {
+                let value_ids = &observation.facet_value_ids;
+                (0..value_ids.len())
+                    .filter(|&i| !value_ids[..i].contains(&value_ids[i]))
+                    .count()
+            })
+            .sum();
+        entry_count == value_count as u64
+    }
+
+    // Uses the stored facet index when it covers the observations, otherwise builds a temporary
+    // index.
+    pub(crate) fn index(&self) -> Cow<'_, FacetIndex> {
+        match &self.facet_index {
+            Some(index) if index.observation_count == self.observation_count() => {
+                Cow::Borrowed(index)
+            }
+            _ => Cow::Owned(FacetIndex::build(self.observations())),
+        }
+    }
+
+    // Groups selected facet values by the id of the facet they belong to. Values that aren't
+    // part of any facet are grouped under `None`. Selecting a value also selects all of its
+    // descendants in the facet's value hierarchy.
+    pub(crate) fn selected_values_by_facet(
+        &self,
+        filter: &Filter,
+    ) -> FxHashMap<Option<DbID>, Vec<DbID>> {
+        let mut value_facets: FxHashMap<DbID, &Facet> = FxHashMap::default();
+        for facet in &self.facets {
+            if let Some(values) = &facet.values {
+                for value_id in values.keys() {
+                    value_facets.insert(*value_id, facet);
+                }
+            }
+        }
+
+        let mut selected: FxHashMap<Option<DbID>, Vec<DbID>> = FxHashMap::default();
+        for value_id in &filter.categorical_facets {
+            match value_facets.get(value_id) {
+                Some(facet) => selected
+                    .entry(Some(facet.id))
+                    .or_default()
+                    .extend(facet.value_and_descendants(*value_id)),
+                None => selected.entry(None).or_default().push(*value_id),
+            }
+        }
+        selected
+    }
+
+    // Removes the positions of observations outside of the filter's numeric intervals
+    pub(crate) fn retain_numeric_matches(&self, filter: &Filter, positions: &mut RoaringBitmap) {
+        if filter.numeric_intervals.is_none() && filter.numeric_facets.is_empty() {
+            return;
+        }
+
+        *positions = positions
+            .iter()
+            .filter(|position| {
+                let in_intervals = match (&filter.numeric_intervals, self.observation(*position)) {
+                    (Some(intervals), Some(observation)) => intervals.contains(observation),
+                    (None, Some(_)) => true,
+                    (_, None) => false,
+                };
+                in_intervals
+                    && filter.numeric_facets.iter().all(|(facet_id, range)| {
+                        match self.numeric_value(*position, NumericField::Facet(*facet_id)) {
+                            Some(value) => value >= range.0 && value <= range.1,
+                            None => false,
+                        }
+                    })
+            })
+            .collect();
+    }
+
+    // Returns the positions of the observations that match the filter
+    pub fn filter(&self, filter: &Filter) -> RoaringBitmap {
+        let index = self.index();
+        let mut matches = index.all();
+        for value_ids in self.selected_values_by_facet(filter).values() {
+            matches &= index.with_any(value_ids);
+        }
+        self.retain_numeric_matches(filter, &mut matches);
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_hash::{FxHashMap, FxHashSet};
+
+    use crate::data_structures::coverage_data::test_data::{
+        categorical_facet, coverage_data, observation,
+    };
+    use crate::data_structures::{CoverageData, Filter};
+
+    #[test]
+    fn test_filter_or_within_and_across_facets() {
+        let mut data = coverage_data();
+
+        let filter = Filter::new(FxHashSet::from_iter([10, 11]));
+        assert_eq!(data.filter(&filter).iter().collect::<Vec<_>>(), [0, 1, 3]);
+
+        let filter = Filter::new(FxHashSet::from_iter([10, 11, 21]));
+        assert_eq!(data.filter(&filter).iter().collect::<Vec<_>>(), [3]);
+
+        let unindexed = data.filter(&filter);
+        data.build_facet_index();
+        assert_eq!(data.filter(&filter), unindexed);
+        assert_eq!(data.filter(&Filter::default()).len(), 4);
+    }
+
+    #[test]
+    fn test_stale_facet_index() {
+        let mut data = CoverageData::new(
+            vec![observation(0, vec![10, 20]), observation(1, vec![11])],
+            vec![observation(2, vec![10])],
+            2_000_000,
+            vec![],
+            vec![categorical_facet(1, &[10, 11]), categorical_facet(2, &[20])],
+            vec![],
+            FxHashMap::default(),
+        );
+        data.build_facet_index();
+        assert!(data.facet_index_is_valid());
+        let filter = Filter::new(FxHashSet::from_iter([10]));
+        let expected = data.filter(&filter);
+
+        // Dropping an observation without rebuilding the index
+        let removed = data.nonsignificant_observations.pop().unwrap();
+        assert!(!data.facet_index_is_valid());
+        assert_eq!(data.filter(&filter).iter().collect::<Vec<_>>(), [0]);
+
+        // Changing an observation's values without rebuilding the index
+        data.nonsignificant_observations.push(removed);
+        data.significant_observations[1].facet_value_ids.push(20);
+        assert!(!data.facet_index_is_valid());
+        data.build_facet_index();
+        assert!(data.facet_index_is_valid());
+        assert_eq!(data.filter(&filter), expected);
+    }
+
+    #[test]
+    fn test_filter_parent_value_matches_descendants() {
+        let mut data = coverage_data();
+        data.facets[0].value_parents = Some([(11, 10), (12, 11)].into_iter().collect());
+        assert!(data.facets[0].validate().is_ok());
+
+        let filter = Filter::new(FxHashSet::from_iter([11]));
+        assert_eq!(data.filter(&filter).iter().collect::<Vec<_>>(), [1, 2]);
+
+        let counts = data.facet_value_counts(&Filter::default());
+        assert_eq!(counts[&10].observations, 4);
+        assert_eq!(counts[&12].observations, 1);
+
+        data.facets[0].value_parents = Some([(11, 12), (12, 11)].into_iter().collect());
+        assert!(data.facets[0].validate().is_err());
+    }
+}
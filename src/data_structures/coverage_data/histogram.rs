@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data_structures::{
+    CoverageData, Facet, FacetRange, FacetRange64, Filter, NumericField, WellKnownFacet,
+};
+
+// Equal-width bins covering [min, max]. The last bin also includes `max`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Histogram {
+    pub min: f64,
+    pub max: f64,
+    pub bins: Vec<u64>,
+}
+
+impl Histogram {
+    pub fn new(bin_count: usize, min: f64, max: f64) -> Self {
+        Histogram {
+            min,
+            max,
+            bins: vec![0; bin_count],
+        }
+    }
+
+    pub fn bin_width(&self) -> f64 {
+        (self.max - self.min) / self.bins.len() as f64
+    }
+
+    // NaN and out of range values aren't counted
+    pub fn add(&mut self, value: f64) {
+        if self.bins.is_empty() || value.is_nan() || value < self.min || value > self.max {
+            return;
+        }
+
+        let bin = if self.max > self.min {
+            ((value - self.min) / self.bin_width()) as usize
+        } else {
+            0
+        };
+        let last_bin = self.bins.len() - 1;
+        self.bins[bin.min(last_bin)] += 1;
+    }
+}
+
+impl CoverageData {
+    pub fn facet_by_name(&self, name: &str) -> Option<&Facet> {
+        self.facets.iter().find(|facet| facet.name == name)
+    }
+
+    pub fn well_known_facet(&self, well_known: WellKnownFacet) -> Option<&Facet> {
+        self.facet_by_name(well_known.name())
+    }
+
+    // Histogram of a numeric field of the filtered observations. Without an explicit range the
+    // stored range of the field's facet is used; if neither is available there's no histogram.
+    pub fn numeric_histogram(
+        &self,
+        filter: &Filter,
+        field: NumericField,
+        bin_count: usize,
+        range: Option<FacetRange64>,
+    ) -> Option<Histogram> {
+        let range = range.or_else(|| self.numeric_field_range(field))?;
+
+        let mut histogram = Histogram::new(bin_count, range.0, range.1);
+        for position in &self.filter(filter) {
+            if let Some(value) = self.numeric_value(position, field) {
+                histogram.add(value);
+            }
+        }
+        Some(histogram)
+    }
+
+    pub fn effect_size_histogram(
+        &self,
+        filter: &Filter,
+        bin_count: usize,
+        range: Option<FacetRange>,
+    ) -> Option<Histogram> {
+        let range = range.map(|range| FacetRange64(range.0 as f64, range.1 as f64));
+        self.numeric_histogram(filter, NumericField::EffectSize, bin_count, range)
+    }
+
+    // Histogram of the -log10 significance of the filtered observations
+    pub fn significance_histogram(
+        &self,
+        filter: &Filter,
+        bin_count: usize,
+        range: Option<FacetRange64>,
+    ) -> Option<Histogram> {
+        self.numeric_histogram(filter, NumericField::NegLogSignificance, bin_count, range)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_hash::FxHashSet;
+
+    use super::Histogram;
+    use crate::data_structures::coverage_data::test_data::{categorical_facet, coverage_data};
+    use crate::data_structures::{FacetRange, FacetRange64, FacetType, Filter, WellKnownFacet};
+
+    #[test]
+    fn test_histogram_bins() {
+        let mut histogram = Histogram::new(4, 0.0, 2.0);
+        for value in [0.0, 0.49, 0.5, 1.9, 2.0, 2.1, -0.1, f64::NAN] {
+            histogram.add(value);
+        }
+        assert_eq!(histogram.bins, vec![2, 1, 0, 2]);
+    }
+
+    #[test]
+    fn test_histograms_use_facet_ranges() {
+        let mut data = coverage_data();
+        let filter = Filter::new(FxHashSet::from_iter([10]));
+        assert!(data.effect_size_histogram(&filter, 3, None).is_none());
+        assert!(data.significance_histogram(&filter, 4, None).is_none());
+
+        for (position, (effect_size, neg_log_significance)) in
+            [(-1.0, 3.0), (0.5, 2.0), (1.0, 0.5), (2.0, 0.1)]
+                .into_iter()
+                .enumerate()
+        {
+            let observation = if position < 2 {
+                &mut data.significant_observations[position]
+            } else {
+                &mut data.nonsignificant_observations[position - 2]
+            };
+            observation.effect_size = effect_size;
+            observation.neg_log_significance = neg_log_significance;
+        }
+
+        let mut effect_size = categorical_facet(3, &[]);
+        effect_size.name = WellKnownFacet::EffectSize.name().to_string();
+        effect_size.facet_type = FacetType::Numeric;
+        effect_size.values = None;
+        effect_size.range = Some(FacetRange(-1.0, 2.0));
+        let mut significance = effect_size.clone();
+        significance.id = 4;
+        significance.name = WellKnownFacet::Significance.name().to_string();
+        significance.facet_type = FacetType::Numeric64;
+        significance.range = None;
+        significance.range64 = Some(FacetRange64(0.0, 4.0));
+        data.facets.extend([effect_size, significance]);
+
+        // Only observations 0 and 3 have value 10
+        let histogram = data.effect_size_histogram(&filter, 3, None).unwrap();
+        assert_eq!((histogram.min, histogram.max), (-1.0, 2.0));
+        assert_eq!(histogram.bins, vec![1, 0, 1]);
+        let histogram = data.significance_histogram(&filter, 4, None).unwrap();
+        assert_eq!((histogram.min, histogram.max), (0.0, 4.0));
+        assert_eq!(histogram.bins, vec![1, 0, 0, 1]);
+
+        // An explicit range takes precedence
+        let histogram = data
+            .effect_size_histogram(&Filter::default(), 2, Some(FacetRange(0.0, 1.0)))
+            .unwrap();
+        assert_eq!(histogram.bins, vec![0, 2]);
+    }
+}
@@ -0,0 +1,348 @@
+use std::io::{self, Write};
+
+use roaring::RoaringBitmap;
+use serde::{Deserialize, Serialize};
+
+use crate::data_structures::{CoverageData, DbID, Facet, FeatureCoordinates, FeatureLocation};
+
+// The names of the BEDPE columns, for the header line
+pub(crate) const BEDPE_COLUMNS: [&str; 11] = [
+    "chrom1",
+    "start1",
+    "end1",
+    "chrom2",
+    "start2",
+    "end2",
+    "name",
+    "score",
+    "strand1",
+    "strand2",
+    "effect_size",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InteractionFormat {
+    // BEDPE, with the REO id as the name, -log10 significance as the score and the effect size
+    // as an extra column
+    Bedpe,
+    // The UCSC `interact` format, ready for bedToBigBed. Scores are -log10 significance scaled
+    // to 0-1000, and interactions are red if the effect size is positive and blue otherwise.
+    Interact,
+    // The WashU Epigenome Browser `longrange` format, scored by -log10 significance
+    Longrange,
+}
+
+struct Interaction<'a> {
+    reo_id: DbID,
+    source_chrom: &'a str,
+    source: &'a FeatureLocation,
+    source_name: String,
+    target_chrom: &'a str,
+    target: &'a FeatureLocation,
+    target_name: String,
+    effect_size: f32,
+    neg_log_significance: f64,
+    facet_value_ids: &'a [DbID],
+}
+
+fn feature_name(feature_id: DbID, location: &FeatureLocation) -> String {
+    location
+        .name
+        .clone()
+        .unwrap_or_else(|| feature_id.to_string())
+}
+
+impl CoverageData {
+    pub fn chrom_name(&self, index: u8) -> Option<&str> {
+        self.chromosomes
+            .iter()
+            .find(|chrom| chrom.index == index)
+            .map(|chrom| chrom.chrom.as_str())
+    }
+
+    // Observations without a target, or whose source or target doesn't have coordinates on a
+    // known chromosome, are left out
+    fn interactions<'a>(
+        &'a self,
+        positions: &RoaringBitmap,
+        coordinates: &'a FeatureCoordinates,
+    ) -> Vec<Interaction<'a>> {
+        let locate = |feature_id: DbID| {
+            let location = coordinates.get(feature_id)?;
+            Some((self.chrom_name(location.chrom)?, location))
+        };
+
+        let mut interactions = vec![];
+        for position in positions {
+            let observation = match self.observation(position) {
+                Some(observation) => observation,
+                None => continue,
+            };
+            let target_id = match observation.target_id {
+                Some(target_id) => target_id,
+                None => continue,
+            };
+            let ((source_chrom, source), (target_chrom, target)) =
+                match (locate(observation.source_id), locate(target_id)) {
+                    (Some(source), Some(target)) => (source, target),
+                    _ => continue,
+                };
+
+            interactions.push(Interaction {
+                reo_id: observation.reo_id,
+                source_chrom,
+                source,
+                source_name: feature_name(observation.source_id, source),
+                target_chrom,
+                target,
+                target_name: feature_name(target_id, target),
+                effect_size: observation.effect_size,
+                neg_log_significance: observation.neg_log_significance,
+                facet_value_ids: &observation.facet_value_ids,
+            });
+        }
+        interactions
+    }
+
+    // Writes the source-target observations at the given positions, one per line (two for
+    // interchromosomal interactions in the interact format). Missing significances are written
+    // as "." in BEDPE and as 0 otherwise.
+    pub fn write_interactions<W: Write>(
+        &self,
+        writer: &mut W,
+        positions: &RoaringBitmap,
+        coordinates: &FeatureCoordinates,
+        format: InteractionFormat,
+    ) -> io::Result<()> {
+        let interactions = self.interactions(positions, coordinates);
+        match format {
+            InteractionFormat::Bedpe => write_bedpe(writer, &interactions, None),
+            InteractionFormat::Interact => write_interact(writer, &interactions),
+            InteractionFormat::Longrange => write_longrange(writer, &interactions),
+        }
+    }
+
+    // Writes BEDPE like `write_interactions`, with a header line of column names and an extra
+    // column, named after the facet, with the label of each observation's value of the facet.
+    // Genome browsers can colour the interactions by this column (see `TrackExport`).
+    pub fn write_bedpe_with_facet<W: Write>(
+        &self,
+        writer: &mut W,
+        positions: &RoaringBitmap,
+        coordinates: &FeatureCoordinates,
+        facet_id: DbID,
+    ) -> io::Result<()> {
+        let facet = self
+            .facets
+            .iter()
+            .find(|facet| facet.id == facet_id)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Unknown facet {}", facet_id),
+                )
+            })?;
+        let interactions = self.interactions(positions, coordinates);
+        write_bedpe(writer, &interactions, Some(facet))
+    }
+}
+
+fn significance(interaction: &Interaction) -> f64 {
+    if interaction.neg_log_significance.is_nan() {
+        0.0
+    } else {
+        interaction.neg_log_significance
+    }
+}
+
+// With a facet, the header and facet column are written too. Observations without a value of
+// the facet have "." in that column.
+fn write_bedpe<W: Write>(
+    writer: &mut W,
+    interactions: &[Interaction],
+    facet: Option<&Facet>,
+) -> io::Result<()> {
+    if let Some(facet) = facet {
+        writeln!(writer, "#{}\t{}", BEDPE_COLUMNS.join("\t"), facet.name)?;
+    }
+
+    for interaction in interactions {
+        let (source, target) = (interaction.source, interaction.target);
+        let score = if interaction.neg_log_significance.is_nan() {
+            ".".to_string()
+        } else {
+            interaction.neg_log_significance.to_string()
+        };
+        write!(
+            writer,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t.\t.\t{}",
+            interaction.source_chrom,
+            source.start,
+            source.end,
+            interaction.target_chrom,
+            target.start,
+            target.end,
+            interaction.reo_id,
+            score,
+            interaction.effect_size,
+        )?;
+        if let Some(facet) = facet {
+            let label = interaction
+                .facet_value_ids
+                .iter()
+                .find_map(|value_id| facet.label(*value_id))
+                .unwrap_or(".");
+            write!(writer, "\t{}", label)?;
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+fn write_longrange<W: Write>(writer: &mut W, interactions: &[Interaction]) -> io::Result<()> {
+    for interaction in interactions {
+        let (source, target) = (interaction.source, interaction.target);
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}:{}-{},{}",
+            interaction.source_chrom,
+            source.start,
+            source.end,
+            interaction.target_chrom,
+            target.start,
+            target.end,
+            significance(interaction),
+        )?;
+    }
+    Ok(())
+}
+
+// Intrachromosomal interactions span both features. Interchromosomal ones have a record on
+// each chromosome, covering the feature on that chromosome. Records are sorted by chromosome
+// name and start, as bedToBigBed needs.
+fn write_interact<W: Write>(writer: &mut W, interactions: &[Interaction]) -> io::Result<()> {
+    let max_significance = interactions.iter().map(significance).fold(0.0, f64::max);
+
+    let mut records = vec![];
+    for interaction in interactions {
+        let (source, target) = (interaction.source, interaction.target);
+        let score = if max_significance > 0.0 {
+            (significance(interaction) / max_significance * 1000.0).round() as u32
+        } else {
+            0
+        };
+        let color = if interaction.effect_size > 0.0 {
+            "255,0,0"
+        } else {
+            "0,0,255"
+        };
+        // The columns after chrom, chromStart and chromEnd
+        let fields = format!(
+            "{}\t{}\t{}\t.\t{}\t{}\t{}\t{}\t{}\t.\t{}\t{}\t{}\t{}\t.",
+            interaction.reo_id,
+            score,
+            significance(interaction),
+            color,
+            interaction.source_chrom,
+            source.start,
+            source.end,
+            interaction.source_name,
+            interaction.target_chrom,
+            target.start,
+            target.end,
+            interaction.target_name,
+        );
+
+        if interaction.source_chrom == interaction.target_chrom {
+            let (start, end) = (source.start.min(target.start), source.end.max(target.end));
+            records.push((interaction.source_chrom, start, end, fields));
+        } else {
+            records.push((
+                interaction.source_chrom,
+                source.start,
+                source.end,
+                fields.clone(),
+            ));
+            records.push((interaction.target_chrom, target.start, target.end, fields));
+        }
+    }
+
+    records.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+    for (chrom, start, end, fields) in records {
+        writeln!(writer, "{}\t{}\t{}\t{}", chrom, start, end, fields)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InteractionFormat;
+    use crate::data_structures::coverage_data::test_data::{coverage_data, feature_coordinates};
+    use crate::data_structures::FacetValueDisplay;
+
+    fn write(format: InteractionFormat) -> String {
+        let mut data = coverage_data();
+        data.significant_observations[0].neg_log_significance = 2.0;
+        data.significant_observations[0].effect_size = 1.5;
+        data.nonsignificant_observations[0].neg_log_significance = f64::NAN;
+        let all = data.filter(&Default::default());
+
+        let mut output = vec![];
+        data.write_interactions(&mut output, &all, &feature_coordinates(), format)
+            .unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_interaction_formats() {
+        assert_eq!(
+            write(InteractionFormat::Bedpe),
+            "chr1\t100\t200\tchr2\t4000100\t4000200\t0\t2\t.\t.\t1.5\n\
+             chr2\t100\t200\tchr1\t8000100\t8000200\t2\t.\t.\t.\t0\n"
+        );
+        // Both interactions are interchromosomal, so each has a record on both chromosomes
+        assert_eq!(
+            write(InteractionFormat::Interact),
+            "chr1\t100\t200\t0\t1000\t2\t.\t255,0,0\t\
+             chr1\t100\t200\t0\t.\tchr2\t4000100\t4000200\tGENE100\t.\n\
+             chr1\t8000100\t8000200\t2\t0\t0\t.\t0,0,255\t\
+             chr2\t100\t200\t2\t.\tchr1\t8000100\t8000200\tGENE101\t.\n\
+             chr2\t100\t200\t2\t0\t0\t.\t0,0,255\t\
+             chr2\t100\t200\t2\t.\tchr1\t8000100\t8000200\tGENE101\t.\n\
+             chr2\t4000100\t4000200\t0\t1000\t2\t.\t255,0,0\t\
+             chr1\t100\t200\t0\t.\tchr2\t4000100\t4000200\tGENE100\t.\n"
+        );
+        assert_eq!(
+            write(InteractionFormat::Longrange),
+            "chr1\t100\t200\tchr2:4000100-4000200,2\n\
+             chr2\t100\t200\tchr1:8000100-8000200,0\n"
+        );
+    }
+
+    #[test]
+    fn test_bedpe_with_facet() {
+        let mut data = coverage_data();
+        let display = FacetValueDisplay {
+            order: 0,
+            color: None,
+            label: Some("Enriched".to_string()),
+        };
+        data.facets[0].value_display = Some([(10, display)].into_iter().collect());
+        let all = data.filter(&Default::default());
+
+        let mut output = vec![];
+        data.write_bedpe_with_facet(&mut output, &all, &feature_coordinates(), 1)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "#chrom1\tstart1\tend1\tchrom2\tstart2\tend2\tname\tscore\tstrand1\tstrand2\t\
+             effect_size\tfacet 1\n\
+             chr1\t100\t200\tchr2\t4000100\t4000200\t0\t0\t.\t.\t0\tEnriched\n\
+             chr2\t100\t200\tchr1\t8000100\t8000200\t2\t0\t.\t.\t0\tvalue 12\n"
+        );
+
+        assert!(data
+            .write_bedpe_with_facet(&mut vec![], &all, &feature_coordinates(), 9)
+            .is_err());
+    }
+}
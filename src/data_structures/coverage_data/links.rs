@@ -0,0 +1,178 @@
+use roaring::RoaringBitmap;
+use serde::{Deserialize, Serialize};
+
+use crate::data_structures::{BucketLoc, CoverageData, DbID};
+
+// A region of one chromosome, from `start` up to but not including `end`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Viewport {
+    pub chrom: u8,
+    pub start: u64,
+    pub end: u64,
+}
+
+impl Viewport {
+    pub fn contains(&self, chrom: u8, position: u64) -> bool {
+        chrom == self.chrom && position >= self.start && position < self.end
+    }
+
+    // Whether any of the region of the chromosome between the two positions is in the viewport
+    pub fn overlaps(&self, chrom: u8, a: u64, b: u64) -> bool {
+        chrom == self.chrom && a.min(b) < self.end && a.max(b) >= self.start
+    }
+}
+
+// An arc between the source and target of an observation. Positions are the middle of the
+// feature's bucket on its chromosome.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Link {
+    pub reo_id: DbID,
+    pub source_chrom: u8,
+    pub source_pos: u64,
+    pub target_chrom: u8,
+    pub target_pos: u64,
+    pub effect_size: f32,
+    pub neg_log_significance: f64,
+}
+
+impl CoverageData {
+    // The position of the middle of the bucket on its chromosome. The last bucket of a
+    // chromosome ends at the end of the chromosome.
+    pub fn bucket_midpoint(&self, bucket: BucketLoc) -> u64 {
+        let bucket_size = self.bucket_size as u64;
+        let start = bucket.idx as u64 * bucket_size;
+        let end = match self.chrom_lengths.get(bucket.chrom as usize) {
+            Some(length) => (start + bucket_size).min(*length as u64),
+            None => start + bucket_size,
+        };
+        start + end.saturating_sub(start) / 2
+    }
+
+    // Links for the observations at the given positions that have both a source and a target.
+    // With a viewport, only links with at least one end in the viewport, or that arc over it
+    // between two positions on its chromosome, are kept. With a limit,
+    // only the `max_links` most significant links are kept. Links are ordered from most to least
+    // significant.
+    pub fn links(
+        &self,
+        positions: &RoaringBitmap,
+        viewport: Option<Viewport>,
+        max_links: Option<usize>,
+    ) -> Vec<Link> {
+        let mut links = vec![];
+        for position in positions {
+            let observation = match self.observation(position) {
+                Some(observation) => observation,
+                None => continue,
+            };
+            let target_bucket = observation
+                .target_id
+                .and_then(|target_id| self.feature_buckets.get(&target_id));
+            let (source, target) = match (
+                self.feature_buckets.get(&observation.source_id),
+                target_bucket,
+            ) {
+                (Some(source), Some(target)) => (*source, *target),
+                _ => continue,
+            };
+
+            let link = Link {
+                reo_id: observation.reo_id,
+                source_chrom: source.chrom,
+                source_pos: self.bucket_midpoint(source),
+                target_chrom: target.chrom,
+                target_pos: self.bucket_midpoint(target),
+                effect_size: observation.effect_size,
+                neg_log_significance: observation.neg_log_significance,
+            };
+            if let Some(viewport) = viewport {
+                let in_viewport = if link.source_chrom == link.target_chrom {
+                    viewport.overlaps(link.source_chrom, link.source_pos, link.target_pos)
+                } else {
+                    viewport.contains(link.source_chrom, link.source_pos)
+                        || viewport.contains(link.target_chrom, link.target_pos)
+                };
+                if !in_viewport {
+                    continue;
+                }
+            }
+            links.push(link);
+        }
+
+        // Missing significances sort last
+        let significance = |link: &Link| {
+            if link.neg_log_significance.is_nan() {
+                f64::NEG_INFINITY
+            } else {
+                link.neg_log_significance
+            }
+        };
+        links.sort_by(|a, b| {
+            significance(b)
+                .total_cmp(&significance(a))
+                .then(a.reo_id.cmp(&b.reo_id))
+        });
+        if let Some(max_links) = max_links {
+            links.truncate(max_links);
+        }
+        links
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Viewport;
+    use crate::data_structures::coverage_data::test_data::coverage_data;
+
+    #[test]
+    fn test_links_viewport_and_limit() {
+        let mut data = coverage_data();
+        data.nonsignificant_observations[0].neg_log_significance = 3.0;
+        let all = data.filter(&Default::default());
+
+        let reo_ids: Vec<u64> = data
+            .links(&all, None, None)
+            .iter()
+            .map(|link| link.reo_id)
+            .collect();
+        assert_eq!(reo_ids, [2, 0]);
+        assert_eq!(data.links(&all, None, Some(1))[0].reo_id, 2);
+
+        let viewport = Viewport {
+            chrom: 1,
+            start: 4_000_000,
+            end: 6_000_000,
+        };
+        let links = data.links(&all, Some(viewport), None);
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].reo_id, 0);
+        assert_eq!(
+            (links[0].source_pos, links[0].target_pos),
+            (1_000_000, 5_000_000)
+        );
+
+        // A link from 3Mb to 9Mb of chr1 arcs over the viewport, but one from 1Mb of chr1 to chr2
+        // doesn't
+        data.significant_observations[1].target_id = Some(101);
+        let viewport = Viewport {
+            chrom: 0,
+            start: 4_000_000,
+            end: 6_000_000,
+        };
+        let links = data.links(&all, Some(viewport), None);
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].reo_id, 1);
+        assert_eq!(
+            (links[0].source_pos, links[0].target_pos),
+            (3_000_000, 9_000_000)
+        );
+
+        // The last bucket of chr1 is cut short, so its midpoint moves with the chromosome's end
+        data.chrom_lengths[0] = 9_500_000;
+        let links = data.links(&all, Some(viewport), None);
+        assert_eq!(
+            (links[0].source_pos, links[0].target_pos),
+            (3_000_000, 8_750_000)
+        );
+    }
+}
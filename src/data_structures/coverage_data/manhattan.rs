@@ -0,0 +1,134 @@
+use roaring::RoaringBitmap;
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::data_structures::{BucketLoc, CoverageData, DbID, FacetCoverage};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ManhattanMode {
+    // A point for every observation
+    Observations,
+    // A point for the most significant observation in each bucket
+    BucketMax,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ManhattanPoint {
+    pub chrom: u8,
+    // Position along the whole genome: the chromosome's offset plus the middle of the bucket
+    pub x: u64,
+    pub neg_log_significance: f64,
+    pub reo_id: DbID,
+}
+
+impl CoverageData {
+    // Where each chromosome starts when the chromosomes are laid end to end, by chromosome index
+    pub fn genome_offsets(&self) -> Vec<u64> {
+        let mut offsets = Vec::with_capacity(self.chrom_lengths.len());
+        let mut offset = 0;
+        for length in &self.chrom_lengths {
+            offsets.push(offset);
+            offset += *length as u64;
+        }
+        offsets
+    }
+
+    // The position of the middle of the bucket along the whole genome
+    pub fn genome_position(&self, bucket: BucketLoc, offsets: &[u64]) -> Option<u64> {
+        let offset = offsets.get(bucket.chrom as usize)?;
+        Some(offset + self.bucket_midpoint(bucket))
+    }
+
+    // Manhattan plot points for the sources or targets of the observations at the given
+    // positions, ordered along the genome. Observations without a feature of that kind,
+    // or whose feature isn't in a bucket, are left out.
+    pub fn manhattan_points(
+        &self,
+        positions: &RoaringBitmap,
+        coverage: FacetCoverage,
+        mode: ManhattanMode,
+    ) -> Vec<ManhattanPoint> {
+        let offsets = self.genome_offsets();
+        let mut points = vec![];
+        let mut bucket_points: FxHashMap<BucketLoc, ManhattanPoint> = FxHashMap::default();
+        for position in positions {
+            let observation = match self.observation(position) {
+                Some(observation) if !observation.neg_log_significance.is_nan() => observation,
+                _ => continue,
+            };
+            let bucket = match observation
+                .feature_id(coverage)
+                .and_then(|feature_id| self.feature_buckets.get(&feature_id))
+            {
+                Some(bucket) => *bucket,
+                None => continue,
+            };
+            let x = match self.genome_position(bucket, &offsets) {
+                Some(x) => x,
+                None => continue,
+            };
+
+            let point = ManhattanPoint {
+                chrom: bucket.chrom,
+                x,
+                neg_log_significance: observation.neg_log_significance,
+                reo_id: observation.reo_id,
+            };
+            match mode {
+                ManhattanMode::Observations => points.push(point),
+                ManhattanMode::BucketMax => {
+                    let max_point = bucket_points.entry(bucket).or_insert_with(|| point.clone());
+                    if point.neg_log_significance > max_point.neg_log_significance {
+                        *max_point = point;
+                    }
+                }
+            }
+        }
+
+        points.extend(bucket_points.into_values());
+        points.sort_by_key(|point| (point.x, point.reo_id));
+        points
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ManhattanMode;
+    use crate::data_structures::coverage_data::test_data::coverage_data;
+    use crate::data_structures::FacetCoverage;
+
+    #[test]
+    fn test_manhattan_genome_positions() {
+        let mut data = coverage_data();
+        data.significant_observations[0].neg_log_significance = 2.0;
+        let all = data.filter(&Default::default());
+
+        let points =
+            data.manhattan_points(&all, FacetCoverage::Source, ManhattanMode::Observations);
+        let xs: Vec<u64> = points.iter().map(|point| point.x).collect();
+        assert_eq!(xs, [1_000_000, 3_000_000, 11_000_000, 17_000_000]);
+
+        let points = data.manhattan_points(&all, FacetCoverage::Target, ManhattanMode::BucketMax);
+        let reo_ids: Vec<u64> = points.iter().map(|point| point.reo_id).collect();
+        assert_eq!(reo_ids, [2, 0]);
+        assert_eq!(points[1].neg_log_significance, 2.0);
+    }
+
+    #[test]
+    fn test_manhattan_partial_last_bucket() {
+        // The last buckets of both chromosomes are half as long as the others
+        let mut data = coverage_data();
+        data.chrom_lengths = vec![9_000_000, 7_000_000];
+        let all = data.filter(&Default::default());
+
+        let points =
+            data.manhattan_points(&all, FacetCoverage::Source, ManhattanMode::Observations);
+        let xs: Vec<u64> = points.iter().map(|point| point.x).collect();
+        assert_eq!(xs, [1_000_000, 3_000_000, 10_000_000, 15_500_000]);
+
+        let points =
+            data.manhattan_points(&all, FacetCoverage::Target, ManhattanMode::Observations);
+        let xs: Vec<u64> = points.iter().map(|point| point.x).collect();
+        assert_eq!(xs, [8_500_000, 14_000_000]);
+    }
+}
@@ -1,13 +1,57 @@
 use std::fmt;
 
-use crate::data_structures::{BucketLoc, ChromosomeData, DbID, Facet, ObservationData};
+use crate::data_structures::{
+    BucketLoc, ChromosomeData, DbID, Facet, FacetCoverage, FacetIndex, ObservationData,
+};
 use roaring::RoaringTreemap;
 use rustc_hash::FxHashMap;
 use serde::de::{self, Deserializer, MapAccess, SeqAccess, Visitor};
 use serde::ser::{SerializeStruct, Serializer};
 use serde::{Deserialize, Serialize};
 
+mod bedgraph;
+mod contact_matrix;
+mod facet_counts;
+mod facet_ranges;
+mod feature_density;
+mod feature_export;
+mod feature_sets;
+mod feature_status;
+mod filter;
+mod histogram;
+mod interactions;
+mod links;
+mod manhattan;
+mod numeric_columns;
+mod p_value_adjustment;
+mod qq;
+mod reclassify;
 pub mod serialize;
+#[cfg(test)]
+pub(crate) mod test_data;
+mod track_config;
+mod volcano;
+
+pub use bedgraph::{BucketAggregate, BucketStatistic};
+pub use contact_matrix::{ContactCell, ContactMatrix, CooMatrix, DenseMatrix};
+pub use facet_counts::FacetValueCount;
+pub use facet_ranges::FacetRangeMismatch;
+pub use feature_density::ChromosomeFeatureDensity;
+pub use feature_sets::FeatureSimilarity;
+pub use feature_status::FeatureStatus;
+pub use filter::{Filter, FilterIntervals};
+pub use histogram::Histogram;
+pub use interactions::InteractionFormat;
+pub use links::{Link, Viewport};
+pub use manhattan::{ManhattanMode, ManhattanPoint};
+pub use numeric_columns::{NumericColumn, NumericField};
+pub use p_value_adjustment::{adjust_p_values, CorrectionMethod};
+pub use qq::QqPlot;
+pub use reclassify::SignificanceCutoff;
+pub use track_config::{TrackExport, TrackFile};
+pub use volcano::{VolcanoDownsampling, VolcanoPoint};
+
+use feature_status::{treemap_bytes, treemap_from_bytes};
 
 #[derive(Clone, Debug)]
 pub struct CoverageData {
@@ -18,6 +62,8 @@ pub struct CoverageData {
     pub facets: Vec<Facet>,
     pub chrom_lengths: Vec<usize>,
     pub feature_buckets: FxHashMap<DbID, BucketLoc>,
+    pub facet_index: Option<FacetIndex>,
+    pub numeric_columns: Vec<NumericColumn>,
 }
 
 const COVERAGE_DATA_FIELD_SIG_OBSERVATIONS: &str = "significant_observations";
@@ -27,6 +73,16 @@ const COVERAGE_DATA_FIELD_CHROMOSOMES: &str = "chromosomes";
 const COVERAGE_DATA_FIELD_FACETS: &str = "facets";
 const COVERAGE_DATA_FIELD_CHROM_LENGTHS: &str = "chrom_lengths";
 const COVERAGE_DATA_FIELD_FEATURE_BUCKETS: &str = "feature_buckets";
+const COVERAGE_DATA_FIELD_FACET_INDEX: &str = "facet_index";
+const COVERAGE_DATA_FIELD_NUMERIC_COLUMNS: &str = "numeric_columns";
+
+// Rejects coverage data with invalid facet definitions when it's loaded
+fn validate_facets<E: de::Error>(facets: &[Facet]) -> Result<(), E> {
+    for facet in facets {
+        facet.validate().map_err(de::Error::custom)?;
+    }
+    Ok(())
+}
 
 impl CoverageData {
     pub fn new(
@@ -46,6 +102,8 @@ impl CoverageData {
             facets,
             chrom_lengths,
             feature_buckets,
+            facet_index: None,
+            numeric_columns: vec![],
         }
     }
 
@@ -76,6 +134,8 @@ impl Serialize for CoverageData {
         state.serialize_field(COVERAGE_DATA_FIELD_FACETS, &self.facets)?;
         state.serialize_field(COVERAGE_DATA_FIELD_CHROM_LENGTHS, &self.chrom_lengths)?;
         state.serialize_field(COVERAGE_DATA_FIELD_FEATURE_BUCKETS, &self.feature_buckets)?;
+        state.serialize_field(COVERAGE_DATA_FIELD_FACET_INDEX, &self.facet_index)?;
+        state.serialize_field(COVERAGE_DATA_FIELD_NUMERIC_COLUMNS, &self.numeric_columns)?;
 
         state.end()
     }
@@ -87,15 +147,17 @@ impl<'de> Deserialize<'de> for CoverageData {
         D: Deserializer<'de>,
     {
         #[derive(Deserialize)]
-        #[serde(field_identifier, rename_all = "lowercase")]
+        #[serde(field_identifier, rename_all = "snake_case")]
         enum Field {
-            Significant_Observations,
-            Nonsignificant_Observations,
-            Bucket_Size,
+            SignificantObservations,
+            NonsignificantObservations,
+            BucketSize,
             Chromosomes,
             Facets,
-            Chrom_Lengths,
-            Feature_Buckets,
+            ChromLengths,
+            FeatureBuckets,
+            FacetIndex,
+            NumericColumns,
         }
 
         struct CoverageDataVisitor;
@@ -123,17 +185,24 @@ impl<'de> Deserialize<'de> for CoverageData {
                 let chromosomes = seq
                     .next_element()?
                     .ok_or_else(|| de::Error::invalid_length(0, &self))?;
-                let facets = seq
+                let facets: Vec<Facet> = seq
                     .next_element()?
                     .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                validate_facets(&facets)?;
                 let chrom_lengths = seq
                     .next_element()?
                     .ok_or_else(|| de::Error::invalid_length(1, &self))?;
                 let feature_buckets = seq
                     .next_element()?
                     .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                let facet_index = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                let numeric_columns = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
 
-                Ok(CoverageData::new(
+                let mut coverage_data = CoverageData::new(
                     significant_observations,
                     nonsignificant_observations,
                     bucket_size,
@@ -141,7 +210,18 @@ impl<'de> Deserialize<'de> for CoverageData {
                     facets,
                     chrom_lengths,
                     feature_buckets,
-                ))
+                );
+                coverage_data.facet_index = facet_index;
+                coverage_data.numeric_columns = numeric_columns;
+                coverage_data
+                    .validate_numeric_columns()
+                    .map_err(de::Error::custom)?;
+                if !coverage_data.facet_index_is_valid() {
+                    return Err(de::Error::custom(
+                        "The facet index doesn't match the observations and needs to be rebuilt",
+                    ));
+                }
+                Ok(coverage_data)
             }
 
             fn visit_map<V>(self, mut map: V) -> Result<CoverageData, V::Error>
@@ -155,10 +235,12 @@ impl<'de> Deserialize<'de> for CoverageData {
                 let mut facets = None;
                 let mut chrom_lengths = None;
                 let mut feature_buckets = None;
+                let mut facet_index = None;
+                let mut numeric_columns = None;
 
                 while let Some(key) = map.next_key()? {
                     match key {
-                        Field::Significant_Observations => {
+                        Field::SignificantObservations => {
                             if significant_observations.is_some() {
                                 return Err(de::Error::duplicate_field(
                                     COVERAGE_DATA_FIELD_SIG_OBSERVATIONS,
@@ -166,7 +248,7 @@ impl<'de> Deserialize<'de> for CoverageData {
                             }
                             significant_observations = Some(map.next_value()?);
                         }
-                        Field::Nonsignificant_Observations => {
+                        Field::NonsignificantObservations => {
                             if nonsignificant_observations.is_some() {
                                 return Err(de::Error::duplicate_field(
                                     COVERAGE_DATA_FIELD_NONSIG_OBSERVATIONS,
@@ -174,7 +256,7 @@ impl<'de> Deserialize<'de> for CoverageData {
                             }
                             nonsignificant_observations = Some(map.next_value()?);
                         }
-                        Field::Bucket_Size => {
+                        Field::BucketSize => {
                             if bucket_size.is_some() {
                                 return Err(de::Error::duplicate_field(
                                     COVERAGE_DATA_FIELD_BUCKET_SIZE,
@@ -196,7 +278,7 @@ impl<'de> Deserialize<'de> for CoverageData {
                             }
                             facets = Some(map.next_value()?);
                         }
-                        Field::Chrom_Lengths => {
+                        Field::ChromLengths => {
                             if chrom_lengths.is_some() {
                                 return Err(de::Error::duplicate_field(
                                     COVERAGE_DATA_FIELD_CHROM_LENGTHS,
@@ -204,7 +286,7 @@ impl<'de> Deserialize<'de> for CoverageData {
                             }
                             chrom_lengths = Some(map.next_value()?);
                         }
-                        Field::Feature_Buckets => {
+                        Field::FeatureBuckets => {
                             if feature_buckets.is_some() {
                                 return Err(de::Error::duplicate_field(
                                     COVERAGE_DATA_FIELD_FEATURE_BUCKETS,
@@ -212,6 +294,22 @@ impl<'de> Deserialize<'de> for CoverageData {
                             }
                             feature_buckets = Some(map.next_value()?);
                         }
+                        Field::FacetIndex => {
+                            if facet_index.is_some() {
+                                return Err(de::Error::duplicate_field(
+                                    COVERAGE_DATA_FIELD_FACET_INDEX,
+                                ));
+                            }
+                            facet_index = Some(map.next_value()?);
+                        }
+                        Field::NumericColumns => {
+                            if numeric_columns.is_some() {
+                                return Err(de::Error::duplicate_field(
+                                    COVERAGE_DATA_FIELD_NUMERIC_COLUMNS,
+                                ));
+                            }
+                            numeric_columns = Some(map.next_value()?);
+                        }
                     }
                 }
                 let significant_observations = significant_observations.ok_or_else(|| {
@@ -224,14 +322,15 @@ impl<'de> Deserialize<'de> for CoverageData {
                     .ok_or_else(|| de::Error::missing_field(COVERAGE_DATA_FIELD_BUCKET_SIZE))?;
                 let chromosomes = chromosomes
                     .ok_or_else(|| de::Error::missing_field(COVERAGE_DATA_FIELD_CHROMOSOMES))?;
-                let facets =
+                let facets: Vec<Facet> =
                     facets.ok_or_else(|| de::Error::missing_field(COVERAGE_DATA_FIELD_FACETS))?;
+                validate_facets(&facets)?;
                 let chrom_lengths = chrom_lengths
                     .ok_or_else(|| de::Error::missing_field(COVERAGE_DATA_FIELD_CHROM_LENGTHS))?;
                 let feature_buckets = feature_buckets
                     .ok_or_else(|| de::Error::missing_field(COVERAGE_DATA_FIELD_FEATURE_BUCKETS))?;
 
-                Ok(CoverageData::new(
+                let mut coverage_data = CoverageData::new(
                     significant_observations,
                     nonsignificant_observations,
                     bucket_size,
@@ -239,11 +338,22 @@ impl<'de> Deserialize<'de> for CoverageData {
                     facets,
                     chrom_lengths,
                     feature_buckets,
-                ))
+                );
+                coverage_data.facet_index = facet_index.flatten();
+                coverage_data.numeric_columns = numeric_columns.unwrap_or_default();
+                coverage_data
+                    .validate_numeric_columns()
+                    .map_err(de::Error::custom)?;
+                if !coverage_data.facet_index_is_valid() {
+                    return Err(de::Error::custom(
+                        "The facet index doesn't match the observations and needs to be rebuilt",
+                    ));
+                }
+                Ok(coverage_data)
             }
         }
 
-        const FIELDS: &'static [&'static str] = &[
+        const FIELDS: &[&str] = &[
             COVERAGE_DATA_FIELD_SIG_OBSERVATIONS,
             COVERAGE_DATA_FIELD_NONSIG_OBSERVATIONS,
             COVERAGE_DATA_FIELD_BUCKET_SIZE,
@@ -251,29 +361,54 @@ impl<'de> Deserialize<'de> for CoverageData {
             COVERAGE_DATA_FIELD_FACETS,
             COVERAGE_DATA_FIELD_CHROM_LENGTHS,
             COVERAGE_DATA_FIELD_FEATURE_BUCKETS,
+            COVERAGE_DATA_FIELD_FACET_INDEX,
+            COVERAGE_DATA_FIELD_NUMERIC_COLUMNS,
         ];
         deserializer.deserialize_struct("CoverageData", FIELDS, CoverageDataVisitor)
     }
 }
 
-#[derive(Clone, Debug)]
+// Serialized experiment feature data always has feature statuses, in every format. Bincode
+// files written before statuses were added can only be read with
+// `ExperimentFeatureData::from_bytes`.
+#[derive(Clone, Debug, Default)]
 pub struct ExperimentFeatureData {
     pub sources: RoaringTreemap,
     pub targets: RoaringTreemap,
+    // Empty if the experiment only records which features it included
+    pub source_status: FeatureStatus,
+    pub target_status: FeatureStatus,
 }
 
 const EXPERIMENT_FEATURE_DATA_FIELD_SOURCES: &str = "sources";
 const EXPERIMENT_FEATURE_DATA_FIELD_TARGETS: &str = "targets";
+const EXPERIMENT_FEATURE_DATA_FIELD_SOURCE_STATUS: &str = "source_status";
+const EXPERIMENT_FEATURE_DATA_FIELD_TARGET_STATUS: &str = "target_status";
 
 impl ExperimentFeatureData {
     pub fn new(sources: RoaringTreemap, targets: RoaringTreemap) -> Self {
-        ExperimentFeatureData { sources, targets }
+        ExperimentFeatureData {
+            sources,
+            targets,
+            source_status: FeatureStatus::default(),
+            target_status: FeatureStatus::default(),
+        }
     }
 
-    pub fn default() -> Self {
+    // The sources and targets are every feature with a status
+    pub fn with_status(source_status: FeatureStatus, target_status: FeatureStatus) -> Self {
         ExperimentFeatureData {
-            sources: RoaringTreemap::default(),
-            targets: RoaringTreemap::default(),
+            sources: source_status.all(),
+            targets: target_status.all(),
+            source_status,
+            target_status,
+        }
+    }
+
+    pub fn status(&self, coverage: FacetCoverage) -> &FeatureStatus {
+        match coverage {
+            FacetCoverage::Source => &self.source_status,
+            FacetCoverage::Target => &self.target_status,
         }
     }
 
@@ -290,14 +425,24 @@ impl Serialize for ExperimentFeatureData {
     where
         S: Serializer,
     {
-        let mut state = serializer.serialize_struct("ExperimentFeatureData", 2)?;
+        let mut state = serializer.serialize_struct("ExperimentFeatureData", 4)?;
 
-        let mut source_data = vec![];
-        let _ = self.sources.serialize_into(&mut source_data);
-        state.serialize_field(EXPERIMENT_FEATURE_DATA_FIELD_SOURCES, &source_data)?;
-        let mut target_data = vec![];
-        let _ = self.targets.serialize_into(&mut target_data);
-        state.serialize_field(EXPERIMENT_FEATURE_DATA_FIELD_TARGETS, &target_data)?;
+        state.serialize_field(
+            EXPERIMENT_FEATURE_DATA_FIELD_SOURCES,
+            &treemap_bytes(&self.sources)?,
+        )?;
+        state.serialize_field(
+            EXPERIMENT_FEATURE_DATA_FIELD_TARGETS,
+            &treemap_bytes(&self.targets)?,
+        )?;
+        state.serialize_field(
+            EXPERIMENT_FEATURE_DATA_FIELD_SOURCE_STATUS,
+            &self.source_status,
+        )?;
+        state.serialize_field(
+            EXPERIMENT_FEATURE_DATA_FIELD_TARGET_STATUS,
+            &self.target_status,
+        )?;
 
         state.end()
     }
@@ -309,10 +454,12 @@ impl<'de> Deserialize<'de> for ExperimentFeatureData {
         D: Deserializer<'de>,
     {
         #[derive(Deserialize)]
-        #[serde(field_identifier, rename_all = "lowercase")]
+        #[serde(field_identifier, rename_all = "snake_case")]
         enum Field {
             Sources,
             Targets,
+            SourceStatus,
+            TargetStatus,
         }
 
         struct ExperimentFeatureDataVisitor;
@@ -330,14 +477,25 @@ impl<'de> Deserialize<'de> for ExperimentFeatureData {
             {
                 let source_data: Vec<u8> = seq
                     .next_element()?
-                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
                 let target_data: Vec<u8> = seq
                     .next_element()?
                     .ok_or_else(|| de::Error::invalid_length(1, &self))?;
-                let sources = RoaringTreemap::deserialize_from(&source_data[..]).unwrap();
-                let targets = RoaringTreemap::deserialize_from(&target_data[..]).unwrap();
-
-                Ok(ExperimentFeatureData::new(sources, targets))
+                let source_status = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+                let target_status = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(3, &self))?;
+                let sources = treemap_from_bytes(&source_data)?;
+                let targets = treemap_from_bytes(&target_data)?;
+
+                Ok(ExperimentFeatureData {
+                    sources,
+                    targets,
+                    source_status,
+                    target_status,
+                })
             }
 
             fn visit_map<V>(self, mut map: V) -> Result<ExperimentFeatureData, V::Error>
@@ -346,6 +504,8 @@ impl<'de> Deserialize<'de> for ExperimentFeatureData {
             {
                 let mut source_data: Option<Vec<u8>> = None;
                 let mut target_data: Option<Vec<u8>> = None;
+                let mut source_status: Option<FeatureStatus> = None;
+                let mut target_status: Option<FeatureStatus> = None;
                 while let Some(key) = map.next_key()? {
                     match key {
                         Field::Sources => {
@@ -364,6 +524,22 @@ impl<'de> Deserialize<'de> for ExperimentFeatureData {
                             }
                             target_data = Some(map.next_value()?);
                         }
+                        Field::SourceStatus => {
+                            if source_status.is_some() {
+                                return Err(de::Error::duplicate_field(
+                                    EXPERIMENT_FEATURE_DATA_FIELD_SOURCE_STATUS,
+                                ));
+                            }
+                            source_status = Some(map.next_value()?);
+                        }
+                        Field::TargetStatus => {
+                            if target_status.is_some() {
+                                return Err(de::Error::duplicate_field(
+                                    EXPERIMENT_FEATURE_DATA_FIELD_TARGET_STATUS,
+                                ));
+                            }
+                            target_status = Some(map.next_value()?);
+                        }
                     }
                 }
 
@@ -373,16 +549,29 @@ impl<'de> Deserialize<'de> for ExperimentFeatureData {
                 let target_data = target_data.ok_or_else(|| {
                     de::Error::missing_field(EXPERIMENT_FEATURE_DATA_FIELD_TARGETS)
                 })?;
-                let sources = RoaringTreemap::deserialize_from(&source_data[..]).unwrap();
-                let targets = RoaringTreemap::deserialize_from(&target_data[..]).unwrap();
-
-                Ok(ExperimentFeatureData::new(sources, targets))
+                let source_status = source_status.ok_or_else(|| {
+                    de::Error::missing_field(EXPERIMENT_FEATURE_DATA_FIELD_SOURCE_STATUS)
+                })?;
+                let target_status = target_status.ok_or_else(|| {
+                    de::Error::missing_field(EXPERIMENT_FEATURE_DATA_FIELD_TARGET_STATUS)
+                })?;
+                let sources = treemap_from_bytes(&source_data)?;
+                let targets = treemap_from_bytes(&target_data)?;
+
+                Ok(ExperimentFeatureData {
+                    sources,
+                    targets,
+                    source_status,
+                    target_status,
+                })
             }
         }
 
-        const FIELDS: &'static [&'static str] = &[
+        const FIELDS: &[&str] = &[
             EXPERIMENT_FEATURE_DATA_FIELD_SOURCES,
             EXPERIMENT_FEATURE_DATA_FIELD_TARGETS,
+            EXPERIMENT_FEATURE_DATA_FIELD_SOURCE_STATUS,
+            EXPERIMENT_FEATURE_DATA_FIELD_TARGET_STATUS,
         ];
         deserializer.deserialize_struct(
             "ExperimentFeatureData",
@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data_structures::{
+    CoverageData, DbID, Facet, FacetError, FacetRange64, FacetType, WellKnownFacet,
+};
+
+// The values of a numeric facet, one for each observation in observation position order
+// (see `CoverageData::observation`). Observations without a value are NaN.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NumericColumn {
+    pub facet_id: DbID,
+    pub values: Vec<f64>,
+}
+
+// A per-observation number that can be filtered on or binned
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum NumericField {
+    EffectSize,
+    // The raw p value
+    Significance,
+    NegLogSignificance,
+    // A numeric facet with a `NumericColumn`
+    Facet(DbID),
+}
+
+impl CoverageData {
+    pub fn numeric_column(&self, facet_id: DbID) -> Option<&NumericColumn> {
+        self.numeric_columns
+            .iter()
+            .find(|column| column.facet_id == facet_id)
+    }
+
+    // Adds (or replaces) a numeric facet and its values for every observation
+    pub fn add_numeric_column(&mut self, facet: Facet, values: Vec<f64>) -> Result<(), FacetError> {
+        if !facet.facet_type.is_numeric() {
+            return Err(FacetError::WrongFacetType {
+                facet: facet.name,
+                expected: FacetType::Numeric64,
+                found: facet.facet_type,
+            });
+        }
+        if values.len() != self.observation_count() as usize {
+            return Err(FacetError::WrongValueCount {
+                facet_id: facet.id,
+                expected: self.observation_count() as usize,
+                found: values.len(),
+            });
+        }
+
+        let column = NumericColumn {
+            facet_id: facet.id,
+            values,
+        };
+        match self
+            .numeric_columns
+            .iter_mut()
+            .find(|c| c.facet_id == facet.id)
+        {
+            Some(existing_column) => *existing_column = column,
+            None => self.numeric_columns.push(column),
+        }
+        match self.facets.iter_mut().find(|f| f.id == facet.id) {
+            Some(existing_facet) => *existing_facet = facet,
+            None => self.facets.push(facet),
+        }
+
+        Ok(())
+    }
+
+    // Every numeric column must belong to a numeric facet and have a value for every observation
+    pub fn validate_numeric_columns(&self) -> Result<(), FacetError> {
+        for column in &self.numeric_columns {
+            match self.facets.iter().find(|facet| facet.id == column.facet_id) {
+                Some(facet) if !facet.facet_type.is_numeric() => {
+                    return Err(FacetError::WrongFacetType {
+                        facet: facet.name.clone(),
+                        expected: FacetType::Numeric64,
+                        found: facet.facet_type,
+                    })
+                }
+                Some(_) => (),
+                None => return Err(FacetError::UnknownFacet(column.facet_id.to_string())),
+            }
+
+            if column.values.len() != self.observation_count() as usize {
+                return Err(FacetError::WrongValueCount {
+                    facet_id: column.facet_id,
+                    expected: self.observation_count() as usize,
+                    found: column.values.len(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    // Returns None if the observation doesn't have a value for the field
+    pub fn numeric_value(&self, position: u32, field: NumericField) -> Option<f64> {
+        let value = match field {
+            NumericField::EffectSize => self.observation(position)?.effect_size as f64,
+            NumericField::Significance => self.observation(position)?.significance,
+            NumericField::NegLogSignificance => self.observation(position)?.neg_log_significance,
+            NumericField::Facet(facet_id) => *self
+                .numeric_column(facet_id)?
+                .values
+                .get(position as usize)?,
+        };
+
+        if value.is_nan() {
+            None
+        } else {
+            Some(value)
+        }
+    }
+
+    // The stored range of the facet the field's values belong to
+    pub fn numeric_field_range(&self, field: NumericField) -> Option<FacetRange64> {
+        let facet = match field {
+            NumericField::EffectSize => self.well_known_facet(WellKnownFacet::EffectSize)?,
+            NumericField::Significance => self.well_known_facet(WellKnownFacet::RawPValue)?,
+            NumericField::NegLogSignificance => {
+                self.well_known_facet(WellKnownFacet::Significance)?
+            }
+            NumericField::Facet(facet_id) => self.facets.iter().find(|f| f.id == facet_id)?,
+        };
+
+        facet.range64.or_else(|| {
+            facet
+                .range
+                .map(|range| FacetRange64(range.0 as f64, range.1 as f64))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_hash::FxHashSet;
+
+    use crate::data_structures::coverage_data::test_data::coverage_data;
+    use crate::data_structures::{Facet, FacetRange64, FacetType, Filter, NumericField};
+
+    #[test]
+    fn test_numeric_column_filter_and_histogram() {
+        let mut data = coverage_data();
+        let base_mean = Facet {
+            id: 3,
+            name: "Base Mean".to_string(),
+            facet_type: FacetType::Numeric64,
+            description: "".to_string(),
+            coverage: None,
+            range: None,
+            range64: None,
+            values: None,
+            value_parents: None,
+            value_display: None,
+        };
+        assert!(data
+            .add_numeric_column(base_mean.clone(), vec![1.0, 2.0])
+            .is_err());
+        data.add_numeric_column(base_mean, vec![1.0, 2.0, f64::NAN, 4.0])
+            .unwrap();
+
+        let filter =
+            Filter::new(FxHashSet::default()).with_numeric_facet(3, FacetRange64(1.5, 10.0));
+        assert_eq!(data.filter(&filter).iter().collect::<Vec<_>>(), [1, 3]);
+
+        data.update_facet_ranges();
+        let histogram = data
+            .numeric_histogram(&Filter::default(), NumericField::Facet(3), 3, None)
+            .unwrap();
+        assert_eq!(histogram.bins, vec![1, 1, 1]);
+        assert!(data.validate_facet_ranges().is_empty());
+    }
+}
@@ -0,0 +1,211 @@
+use roaring::RoaringBitmap;
+use serde::{Deserialize, Serialize};
+
+use crate::data_structures::{CoverageData, DbID, Facet, FacetError, FacetType, NumericField};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CorrectionMethod {
+    BenjaminiHochberg,
+    Bonferroni,
+    // Storey's q values. The proportion of true null hypotheses is estimated from the
+    // p values above lambda (usually 0.5).
+    StoreyQ { lambda: f64 },
+}
+
+impl CorrectionMethod {
+    pub fn description(&self) -> String {
+        match self {
+            CorrectionMethod::BenjaminiHochberg => {
+                "Benjamini-Hochberg adjusted p value".to_string()
+            }
+            CorrectionMethod::Bonferroni => "Bonferroni adjusted p value".to_string(),
+            CorrectionMethod::StoreyQ { lambda } => format!("Storey q value (lambda = {})", lambda),
+        }
+    }
+}
+
+// Benjamini-Hochberg adjusted p values scaled by the estimated proportion of true nulls
+fn step_up(p_values: &[f64], pi0: f64) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..p_values.len())
+        .filter(|i| !p_values[*i].is_nan())
+        .collect();
+    order.sort_by(|a, b| p_values[*a].total_cmp(&p_values[*b]));
+
+    let m = order.len() as f64;
+    let mut adjusted = vec![f64::NAN; p_values.len()];
+    let mut running_min: f64 = 1.0;
+    for (rank, i) in order.iter().enumerate().rev() {
+        running_min = running_min.min(pi0 * m * p_values[*i] / (rank + 1) as f64);
+        adjusted[*i] = running_min;
+    }
+    adjusted
+}
+
+// Adjusts the p values for multiple testing. NaN p values aren't counted as tests and stay NaN.
+pub fn adjust_p_values(p_values: &[f64], method: CorrectionMethod) -> Vec<f64> {
+    match method {
+        CorrectionMethod::BenjaminiHochberg => step_up(p_values, 1.0),
+        CorrectionMethod::Bonferroni => {
+            let m = p_values.iter().filter(|p| !p.is_nan()).count() as f64;
+            p_values
+                .iter()
+                .map(|p| {
+                    if p.is_nan() {
+                        f64::NAN
+                    } else {
+                        (p * m).min(1.0)
+                    }
+                })
+                .collect()
+        }
+        CorrectionMethod::StoreyQ { lambda } => {
+            let tested: Vec<f64> = p_values.iter().copied().filter(|p| !p.is_nan()).collect();
+            let above_lambda = tested.iter().filter(|p| **p > lambda).count() as f64;
+            // Without any p values above lambda the estimate would be 0, making every q value 0,
+            // so like the qvalue package pi0 is at least 1 / m
+            let m = tested.len() as f64;
+            let pi0 = if tested.is_empty() || lambda >= 1.0 {
+                1.0
+            } else {
+                (above_lambda / (m * (1.0 - lambda))).clamp(1.0 / m, 1.0)
+            };
+            step_up(p_values, pi0)
+        }
+    }
+}
+
+impl CoverageData {
+    // Adjusts the raw p values of the observations at `positions` (or all observations) and
+    // stores them as a new numeric facet, which can then be used in a `SignificanceCutoff`.
+    // Observations that aren't adjusted don't have a value.
+    pub fn add_adjusted_p_values(
+        &mut self,
+        facet_id: DbID,
+        name: &str,
+        method: CorrectionMethod,
+        positions: Option<&RoaringBitmap>,
+    ) -> Result<(), FacetError> {
+        if self.facets.iter().any(|facet| facet.id == facet_id) {
+            return Err(FacetError::DuplicateFacet(facet_id));
+        }
+
+        let p_values: Vec<f64> = (0..self.observation_count())
+            .map(|position| match positions {
+                Some(positions) if !positions.contains(position) => f64::NAN,
+                _ => self
+                    .numeric_value(position, NumericField::Significance)
+                    .unwrap_or(f64::NAN),
+            })
+            .collect();
+
+        let facet = Facet {
+            id: facet_id,
+            name: name.to_string(),
+            facet_type: FacetType::Numeric64,
+            description: method.description(),
+            coverage: None,
+            range: None,
+            range64: None,
+            values: None,
+            value_parents: None,
+            value_display: None,
+        };
+        self.add_numeric_column(facet, adjust_p_values(&p_values, method))?;
+
+        let range = self.observed_range(NumericField::Facet(facet_id));
+        if let Some(facet) = self.facets.iter_mut().find(|f| f.id == facet_id) {
+            facet.range64 = range;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{adjust_p_values, CorrectionMethod};
+    use crate::data_structures::coverage_data::test_data::coverage_data;
+    use crate::data_structures::FacetError;
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                (a - e).abs() < 1e-12 || (a.is_nan() && e.is_nan()),
+                "{a} != {e}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_adjust_p_values() {
+        let p_values = [0.01, 0.04, f64::NAN, 0.03, 0.005];
+        assert_close(
+            &adjust_p_values(&p_values, CorrectionMethod::BenjaminiHochberg),
+            &[0.02, 0.04, f64::NAN, 0.04, 0.02],
+        );
+        assert_close(
+            &adjust_p_values(&p_values, CorrectionMethod::Bonferroni),
+            &[0.04, 0.16, f64::NAN, 0.12, 0.02],
+        );
+
+        // With half of the p values above lambda, pi0 is 1
+        let p_values = [0.01, 0.02, 0.6, 0.8];
+        assert_close(
+            &adjust_p_values(&p_values, CorrectionMethod::StoreyQ { lambda: 0.5 }),
+            &adjust_p_values(&p_values, CorrectionMethod::BenjaminiHochberg),
+        );
+
+        // Two of the six p values are above lambda, so pi0 = 2 / (6 * 0.5) = 2/3. The
+        // Benjamini-Hochberg adjusted p values are 0.06 (x4), 0.72 and 0.8.
+        let p_values = [0.01, 0.02, f64::NAN, 0.03, 0.04, 0.6, 0.8];
+        assert_close(
+            &adjust_p_values(&p_values, CorrectionMethod::StoreyQ { lambda: 0.5 }),
+            &[0.04, 0.04, f64::NAN, 0.04, 0.04, 0.48, 0.8 * 2.0 / 3.0],
+        );
+
+        // Every p value is at or below lambda, so pi0 is floored at 1/4. The
+        // Benjamini-Hochberg adjusted p values are 0.04, 0.4 (x2) and 0.5.
+        let p_values = [0.01, 0.2, 0.3, 0.5];
+        assert_close(
+            &adjust_p_values(&p_values, CorrectionMethod::StoreyQ { lambda: 0.5 }),
+            &[0.01, 0.1, 0.1, 0.125],
+        );
+    }
+
+    #[test]
+    fn test_add_adjusted_p_values() {
+        let mut data = coverage_data();
+        for (observation, p_value) in data
+            .significant_observations
+            .iter_mut()
+            .chain(data.nonsignificant_observations.iter_mut())
+            .zip([0.01, 0.02, 0.5, f64::NAN])
+        {
+            observation.significance = p_value;
+        }
+
+        data.add_adjusted_p_values(3, "Bonferroni", CorrectionMethod::Bonferroni, None)
+            .unwrap();
+        assert_close(
+            &data.numeric_column(3).unwrap().values,
+            &[0.03, 0.06, 1.0, f64::NAN],
+        );
+        let range = data.facets[2].range64.unwrap();
+        assert_eq!((range.0, range.1), (0.03, 1.0));
+
+        // Existing facets, categorical or not, aren't replaced
+        for facet_id in [1, 3] {
+            assert!(matches!(
+                data.add_adjusted_p_values(
+                    facet_id,
+                    "BH",
+                    CorrectionMethod::BenjaminiHochberg,
+                    None
+                ),
+                Err(FacetError::DuplicateFacet(id)) if id == facet_id
+            ));
+        }
+        assert!(data.facets[0].values.is_some());
+        assert_close(&data.numeric_column(3).unwrap().values[..1], &[0.03]);
+    }
+}
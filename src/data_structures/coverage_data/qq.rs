@@ -0,0 +1,141 @@
+use roaring::RoaringBitmap;
+use serde::{Deserialize, Serialize};
+
+use crate::data_structures::{CoverageData, NumericField};
+
+// The median of the chi-squared distribution with one degree of freedom
+const CHI_SQUARED_MEDIAN: f64 = 0.454_936_423_119_572_7;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QqPlot {
+    // Expected -log10 p value quantiles under the null hypothesis, ascending
+    pub expected: Vec<f64>,
+    // Observed -log10 p values, ascending
+    pub observed: Vec<f64>,
+    // The genomic inflation factor: the median observed chi-squared statistic over the median
+    // expected under the null. Values well above 1 suggest the p values are miscalibrated.
+    pub lambda_gc: f64,
+}
+
+// The inverse of the standard normal CDF, using Acklam's rational approximation (relative
+// error below 1.2e-9)
+fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    const P_LOW: f64 = 0.024_25;
+
+    if p < P_LOW {
+        let q = (-2.0 * p.ln()).sqrt();
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    } else if p <= 1.0 - P_LOW {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    } else {
+        -normal_quantile(1.0 - p)
+    }
+}
+
+// The chi-squared statistic (one degree of freedom) with the given upper tail p value
+fn chi_squared(p_value: f64) -> f64 {
+    normal_quantile(p_value / 2.0).powi(2)
+}
+
+fn median(sorted_values: &[f64]) -> f64 {
+    let middle = sorted_values.len() / 2;
+    if sorted_values.len().is_multiple_of(2) {
+        (sorted_values[middle - 1] + sorted_values[middle]) / 2.0
+    } else {
+        sorted_values[middle]
+    }
+}
+
+impl QqPlot {
+    // NaN p values are ignored, and p values of 0 are treated as the smallest positive f64.
+    // There's no plot without any p values.
+    pub fn from_p_values(p_values: impl Iterator<Item = f64>) -> Option<Self> {
+        let mut p_values: Vec<f64> = p_values
+            .filter(|p| !p.is_nan())
+            .map(|p| p.clamp(f64::MIN_POSITIVE, 1.0))
+            .collect();
+        if p_values.is_empty() {
+            return None;
+        }
+        // Descending p values are ascending -log10 p values
+        p_values.sort_by(|a, b| b.total_cmp(a));
+
+        let n = p_values.len() as f64;
+        let expected = (0..p_values.len())
+            .rev()
+            .map(|i| -((i as f64 + 0.5) / n).log10())
+            .collect();
+        let observed = p_values.iter().map(|p| -p.log10()).collect();
+
+        // Also ascending, since the statistic grows as the p value shrinks
+        let chi_squared_values: Vec<f64> = p_values.iter().map(|p| chi_squared(*p)).collect();
+        let lambda_gc = median(&chi_squared_values) / CHI_SQUARED_MEDIAN;
+
+        Some(QqPlot {
+            expected,
+            observed,
+            lambda_gc,
+        })
+    }
+}
+
+impl CoverageData {
+    // QQ plot of the raw p values of the observations at the given positions, which should
+    // include both significant and nonsignificant observations to be meaningful.
+    pub fn qq_plot(&self, positions: &RoaringBitmap) -> Option<QqPlot> {
+        QqPlot::from_p_values(
+            positions
+                .iter()
+                .filter_map(|position| self.numeric_value(position, NumericField::Significance)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chi_squared, QqPlot};
+
+    #[test]
+    fn test_lambda_gc() {
+        assert!((chi_squared(0.05) - 3.841_458_820_694_124).abs() < 1e-6);
+
+        // Uniform p values are perfectly calibrated
+        let n = 1001;
+        let qq_plot = QqPlot::from_p_values((0..n).map(|i| (i as f64 + 0.5) / n as f64)).unwrap();
+        assert!((qq_plot.lambda_gc - 1.0).abs() < 1e-6);
+        assert_eq!(qq_plot.expected, qq_plot.observed);
+    }
+}
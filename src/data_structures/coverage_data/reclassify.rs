@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data_structures::{CoverageData, DbID, NumericField, ObservationData};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SignificanceCutoff {
+    // Significant if the raw p value is at most the cutoff
+    RawPValue(f64),
+    // Significant if the value of a numeric facet, e.g. an adjusted p value, is at most the
+    // cutoff. Observations without a value aren't significant.
+    AdjustedPValue { facet_id: DbID, cutoff: f64 },
+    // Significant if the -log10 significance is at least the cutoff
+    NegLogSignificance(f64),
+}
+
+impl CoverageData {
+    pub fn is_significant(
+        &self,
+        position: u32,
+        cutoff: SignificanceCutoff,
+        min_abs_effect_size: Option<f32>,
+    ) -> bool {
+        let passes_cutoff = match cutoff {
+            SignificanceCutoff::RawPValue(cutoff) => self
+                .numeric_value(position, NumericField::Significance)
+                .is_some_and(|p_value| p_value <= cutoff),
+            SignificanceCutoff::AdjustedPValue { facet_id, cutoff } => self
+                .numeric_value(position, NumericField::Facet(facet_id))
+                .is_some_and(|p_value| p_value <= cutoff),
+            SignificanceCutoff::NegLogSignificance(cutoff) => self
+                .numeric_value(position, NumericField::NegLogSignificance)
+                .is_some_and(|significance| significance >= cutoff),
+        };
+        let passes_effect_size = match min_abs_effect_size {
+            Some(min_effect_size) => self
+                .numeric_value(position, NumericField::EffectSize)
+                .is_some_and(|effect_size| effect_size.abs() >= min_effect_size as f64),
+            None => true,
+        };
+
+        passes_cutoff && passes_effect_size
+    }
+
+    // Splits the observations into significant and nonsignificant observations using a new
+    // cutoff. Observations keep their relative order, and the numeric columns and facet
+    // index (if there is one) are updated to match the new observation positions.
+    pub fn reclassify(&mut self, cutoff: SignificanceCutoff, min_abs_effect_size: Option<f32>) {
+        let (significant_positions, nonsignificant_positions): (Vec<u32>, Vec<u32>) = (0..self
+            .observation_count())
+            .partition(|position| self.is_significant(*position, cutoff, min_abs_effect_size));
+
+        let mut observations: Vec<Option<ObservationData>> = self
+            .significant_observations
+            .drain(..)
+            .chain(self.nonsignificant_observations.drain(..))
+            .map(Some)
+            .collect();
+        let mut take = |positions: &[u32]| -> Vec<ObservationData> {
+            positions
+                .iter()
+                .filter_map(|position| observations[*position as usize].take())
+                .collect()
+        };
+        self.significant_observations = take(&significant_positions);
+        self.nonsignificant_observations = take(&nonsignificant_positions);
+
+        for column in &mut self.numeric_columns {
+            column.values = significant_positions
+                .iter()
+                .chain(nonsignificant_positions.iter())
+                .map(|position| column.values[*position as usize])
+                .collect();
+        }
+
+        if self.facet_index.is_some() {
+            self.build_facet_index();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SignificanceCutoff;
+    use crate::data_structures::coverage_data::test_data::coverage_data;
+    use crate::data_structures::{FacetType, NumericField, ObservationData};
+
+    #[test]
+    fn test_reclassify_moves_observations_and_columns() {
+        let mut data = coverage_data();
+        data.nonsignificant_observations[1].significance = 0.01;
+        data.nonsignificant_observations[1].effect_size = -2.0;
+        data.nonsignificant_observations[0].significance = 0.02;
+        let mut column_facet = data.facets[0].clone();
+        column_facet.id = 3;
+        column_facet.facet_type = FacetType::Numeric64;
+        column_facet.values = None;
+        data.add_numeric_column(column_facet, vec![0.0, 1.0, 2.0, 3.0])
+            .unwrap();
+        data.build_facet_index();
+
+        data.reclassify(SignificanceCutoff::RawPValue(0.05), Some(1.0));
+
+        let reo_ids = |observations: &[ObservationData]| {
+            observations
+                .iter()
+                .map(|obs| obs.reo_id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(reo_ids(&data.significant_observations), [3]);
+        assert_eq!(reo_ids(&data.nonsignificant_observations), [0, 1, 2]);
+        assert_eq!(data.numeric_value(0, NumericField::Facet(3)), Some(3.0));
+        assert_eq!(
+            data.facet_index.unwrap().with_value(21),
+            [0, 3].into_iter().collect()
+        );
+    }
+}
@@ -4,25 +4,131 @@ use std::io::{BufWriter, Write};
 use std::path::PathBuf;
 
 use bincode::Options as BincodeOptions;
+use roaring::RoaringTreemap;
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::de::Error as DeError;
+use serde::Deserialize;
 
-use crate::data_structures::{CoverageData, ExperimentFeatureData};
+use super::validate_facets;
+use crate::data_structures::{
+    BucketLoc, ChromosomeData, CoverageData, DbID, ExperimentFeatureData, Facet, FacetCoverage,
+    FacetRange, FacetRange64, FacetType, ObservationData,
+};
+
+// Coverage files start with this magic number followed by the format version, a little-endian
+// u32. Files without it are format version 1, from before the header was added. A version 1
+// file can't start with 0xff, since bincode never starts an integer with it.
+const COVERAGE_DATA_MAGIC: &[u8] = b"\xffCCOV";
+pub const COVERAGE_DATA_FORMAT_VERSION: u32 = 2;
+
+// A facet as written in format version 1
+#[derive(Deserialize)]
+struct LegacyFacet {
+    id: DbID,
+    name: String,
+    facet_type: FacetType,
+    description: String,
+    coverage: Option<FxHashSet<FacetCoverage>>,
+    range: Option<FacetRange>,
+    range64: Option<FacetRange64>,
+    values: Option<FxHashMap<DbID, String>>,
+}
+
+impl From<LegacyFacet> for Facet {
+    fn from(legacy: LegacyFacet) -> Self {
+        Facet {
+            id: legacy.id,
+            name: legacy.name,
+            facet_type: legacy.facet_type,
+            description: legacy.description,
+            coverage: legacy.coverage,
+            range: legacy.range,
+            range64: legacy.range64,
+            values: legacy.values,
+            value_parents: None,
+            value_display: None,
+        }
+    }
+}
+
+// Coverage data as written in format version 1
+#[derive(Deserialize)]
+struct LegacyCoverageData {
+    significant_observations: Vec<ObservationData>,
+    nonsignificant_observations: Vec<ObservationData>,
+    bucket_size: u32,
+    chromosomes: Vec<ChromosomeData>,
+    facets: Vec<LegacyFacet>,
+    chrom_lengths: Vec<usize>,
+    feature_buckets: FxHashMap<DbID, BucketLoc>,
+}
+
+// Experiment feature data written before feature statuses were added
+#[derive(Deserialize)]
+struct LegacyExperimentFeatureData {
+    sources: Vec<u8>,
+    targets: Vec<u8>,
+}
 
 impl CoverageData {
     pub fn serialize(&self, output_path: &PathBuf) {
-        let bincode_options = bincode::DefaultOptions::new().with_no_limit();
-
         let mut writer = BufWriter::new(File::create(output_path).unwrap());
-        bincode_options.serialize_into(&mut writer, self).unwrap();
+        self.serialize_into(&mut writer).unwrap();
         match writer.flush() {
             Ok(_) => (),
             Err(e) => eprintln!("Error flushing during serialization: {:?}", e),
         };
     }
 
-    pub fn deserialize(file_path: &PathBuf) -> Result<Self, bincode::Error> {
+    // Writes the header and the data, in the format `from_bytes` reads
+    pub fn serialize_into<W: Write>(&self, mut writer: W) -> Result<(), bincode::Error> {
         let bincode_options = bincode::DefaultOptions::new().with_no_limit();
+        writer.write_all(COVERAGE_DATA_MAGIC)?;
+        writer.write_all(&COVERAGE_DATA_FORMAT_VERSION.to_le_bytes())?;
+        bincode_options.serialize_into(writer, self)
+    }
+
+    pub fn deserialize(file_path: &PathBuf) -> Result<Self, bincode::Error> {
         let raw_bytes = fs::read(file_path).unwrap();
-        bincode_options.deserialize(&raw_bytes)
+        CoverageData::from_bytes(&raw_bytes)
+    }
+
+    // Reads the current format and format version 1
+    pub fn from_bytes(raw_bytes: &[u8]) -> Result<Self, bincode::Error> {
+        let bincode_options = bincode::DefaultOptions::new().with_no_limit();
+        let header = match raw_bytes.strip_prefix(COVERAGE_DATA_MAGIC) {
+            Some(header) => header,
+            None => return CoverageData::from_legacy_bytes(raw_bytes),
+        };
+
+        let (version, data) = header
+            .split_at_checked(4)
+            .ok_or_else(|| bincode::Error::custom("missing coverage data format version"))?;
+        let version = u32::from_le_bytes([version[0], version[1], version[2], version[3]]);
+        if version != COVERAGE_DATA_FORMAT_VERSION {
+            return Err(bincode::Error::custom(format!(
+                "unsupported coverage data format version {}",
+                version
+            )));
+        }
+        bincode_options.deserialize(data)
+    }
+
+    fn from_legacy_bytes(raw_bytes: &[u8]) -> Result<Self, bincode::Error> {
+        let bincode_options = bincode::DefaultOptions::new().with_no_limit();
+        let legacy: LegacyCoverageData = bincode_options.deserialize(raw_bytes)?;
+        let facets: Vec<Facet> = legacy.facets.into_iter().map(Facet::from).collect();
+        validate_facets::<bincode::Error>(&facets)?;
+
+        Ok(CoverageData::new(
+            legacy.significant_observations,
+            legacy.nonsignificant_observations,
+            legacy.bucket_size,
+            legacy.chromosomes,
+            facets,
+            legacy.chrom_lengths,
+            legacy.feature_buckets,
+        ))
     }
 }
 
@@ -39,8 +145,252 @@ impl ExperimentFeatureData {
     }
 
     pub fn deserialize(file_path: &PathBuf) -> Result<Self, bincode::Error> {
-        let bincode_options = bincode::DefaultOptions::new().with_no_limit();
         let raw_bytes = fs::read(file_path).unwrap();
-        bincode_options.deserialize(&raw_bytes)
+        ExperimentFeatureData::from_bytes(&raw_bytes)
+    }
+
+    // Reads both the current format and the format without feature statuses
+    pub fn from_bytes(raw_bytes: &[u8]) -> Result<Self, bincode::Error> {
+        let bincode_options = bincode::DefaultOptions::new().with_no_limit();
+        let error = match bincode_options.deserialize(raw_bytes) {
+            Ok(data) => return Ok(data),
+            Err(error) => error,
+        };
+
+        let legacy: LegacyExperimentFeatureData = match bincode_options.deserialize(raw_bytes) {
+            Ok(legacy) => legacy,
+            // The error of the current format is the more useful one
+            Err(_) => return Err(error),
+        };
+        let sources = RoaringTreemap::deserialize_from(&legacy.sources[..])?;
+        let targets = RoaringTreemap::deserialize_from(&legacy.targets[..])?;
+        Ok(ExperimentFeatureData::new(sources, targets))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bincode::Options as BincodeOptions;
+    use roaring::RoaringTreemap;
+
+    use crate::data_structures::coverage_data::test_data::coverage_data;
+    use rustc_hash::FxHashSet;
+    use serde_json::json;
+
+    use crate::data_structures::{
+        CoverageData, ExperimentFeatureData, Facet, FacetType, FeatureStatus, Filter,
+    };
+
+    #[test]
+    fn test_coverage_data_round_trip() {
+        let mut data = coverage_data();
+        data.build_facet_index();
+        let mut base_mean = data.facets[0].clone();
+        base_mean.id = 3;
+        base_mean.facet_type = FacetType::Numeric64;
+        base_mean.values = None;
+        data.add_numeric_column(base_mean, vec![1.0, 2.0, 3.0, 4.0])
+            .unwrap();
+
+        let mut raw_bytes = vec![];
+        data.serialize_into(&mut raw_bytes).unwrap();
+        let loaded = CoverageData::from_bytes(&raw_bytes).unwrap();
+
+        assert_eq!(loaded.observation_count(), 4);
+        assert_eq!(
+            loaded.facet_index.unwrap().facet_values,
+            data.facet_index.unwrap().facet_values
+        );
+        assert_eq!(loaded.numeric_columns[0].values, vec![1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn test_coverage_data_stale_facet_index() {
+        let bincode_options = bincode::DefaultOptions::new().with_no_limit();
+        let mut data =
+            CoverageData::from_bytes(include_bytes!("fixtures/baseline_coverage.ccov")).unwrap();
+        data.build_facet_index();
+        let raw_bytes = bincode_options.serialize(&data).unwrap();
+        assert!(bincode_options
+            .deserialize::<CoverageData>(&raw_bytes)
+            .is_ok());
+
+        data.nonsignificant_observations.pop();
+        let raw_bytes = bincode_options.serialize(&data).unwrap();
+        assert!(bincode_options
+            .deserialize::<CoverageData>(&raw_bytes)
+            .is_err());
+    }
+
+    #[test]
+    fn test_coverage_data_baseline_format() {
+        // Written before the format had a version header
+        let raw_bytes = include_bytes!("fixtures/baseline_coverage.ccov");
+        let data = CoverageData::from_bytes(raw_bytes).unwrap();
+
+        assert_eq!(data.observation_count(), 4);
+        assert_eq!(data.significant_observations[0].reo_id, 0);
+        assert_eq!(data.nonsignificant_observations[1].reo_id, 3);
+        assert_eq!(data.bucket_size, 2_000_000);
+        assert_eq!(data.chrom_lengths, [10_000_000, 8_000_000]);
+        assert_eq!(data.feature_buckets.len(), 5);
+        assert!(data.facet_index.is_none());
+
+        let facet_types: Vec<_> = data.facets.iter().map(|facet| facet.facet_type).collect();
+        assert_eq!(facet_types, [FacetType::Categorical, FacetType::Numeric]);
+        assert_eq!(
+            data.facets[0].values.as_ref().unwrap()[&10],
+            "Enriched Only"
+        );
+        assert!(data.facets[0].value_parents.is_none());
+        assert!(data.facets[0].value_display.is_none());
+        assert_eq!(data.filter(&Default::default()).len(), 4);
+
+        // The current format round-trips
+        let mut raw_bytes = vec![];
+        data.serialize_into(&mut raw_bytes).unwrap();
+        let loaded = CoverageData::from_bytes(&raw_bytes).unwrap();
+        assert_eq!(loaded.observation_count(), 4);
+
+        // Unknown format versions are rejected
+        let mut raw_bytes = b"\xffCCOV".to_vec();
+        raw_bytes.extend(99u32.to_le_bytes());
+        assert!(CoverageData::from_bytes(&raw_bytes).is_err());
+        assert!(CoverageData::from_bytes(b"\xffCC").is_err());
+    }
+
+    #[test]
+    fn test_facet_hierarchy_formats() {
+        let bincode_options = bincode::DefaultOptions::new().with_no_limit();
+        let mut data =
+            CoverageData::from_bytes(include_bytes!("fixtures/baseline_coverage.ccov")).unwrap();
+        assert_eq!(data.facets[0].value_and_descendants(10), [10]);
+        assert_eq!(data.facets[0].label(11), Some("Depleted Only"));
+        assert_eq!(
+            data.facets[0].ordered_values(),
+            [(11, "Depleted Only"), (10, "Enriched Only")]
+        );
+
+        data.facets[0].value_parents = Some([(11, 10)].into_iter().collect());
+        let raw_bytes = bincode_options.serialize(&data).unwrap();
+        let loaded: CoverageData = bincode_options.deserialize(&raw_bytes).unwrap();
+        assert_eq!(loaded.facets[0].parent(11), Some(10));
+        assert_eq!(
+            loaded
+                .filter(&Filter::new(FxHashSet::from_iter([10])))
+                .len(),
+            4
+        );
+
+        // Facets without a hierarchy in self-describing formats
+        let facet: Facet = serde_json::from_value(json!({
+            "id": 1,
+            "name": "Direction",
+            "facet_type": "FacetType.CATEGORICAL",
+            "description": "",
+            "coverage": null,
+            "range": null,
+            "range64": null,
+            "values": {"10": "Enriched Only"},
+        }))
+        .unwrap();
+        assert!(facet.value_parents.is_none());
+        assert!(facet.value_display.is_none());
+    }
+
+    #[test]
+    fn test_experiment_feature_status_round_trip() {
+        let source_status = FeatureStatus {
+            tested: RoaringTreemap::from_iter([1, 2, 3]),
+            significant_positive: RoaringTreemap::from_iter([1]),
+            significant_negative: RoaringTreemap::from_iter([2]),
+            untestable: RoaringTreemap::from_iter([4]),
+        };
+        let data = ExperimentFeatureData::with_status(source_status, FeatureStatus::default());
+        assert_eq!(data.sources, RoaringTreemap::from_iter([1, 2, 3, 4]));
+
+        let bincode_options = bincode::DefaultOptions::new().with_no_limit();
+        let raw_bytes = bincode_options.serialize(&data).unwrap();
+        let loaded = ExperimentFeatureData::from_bytes(&raw_bytes).unwrap();
+        assert_eq!(loaded.source_status, data.source_status);
+        assert_eq!(
+            loaded.source_status.not_significant(),
+            RoaringTreemap::from_iter([3])
+        );
+
+        // Files without statuses still load
+        let mut sources = vec![];
+        data.sources.serialize_into(&mut sources).unwrap();
+        let mut targets = vec![];
+        data.targets.serialize_into(&mut targets).unwrap();
+        let raw_bytes = bincode_options.serialize(&(sources, targets)).unwrap();
+        let loaded = ExperimentFeatureData::from_bytes(&raw_bytes).unwrap();
+        assert_eq!(loaded.sources, data.sources);
+        assert!(loaded.source_status.is_empty());
+    }
+
+    #[test]
+    fn test_experiment_feature_data_baseline_format() {
+        // Written before feature statuses were added
+        let raw_bytes = include_bytes!("fixtures/baseline_features.efd");
+        let loaded = ExperimentFeatureData::from_bytes(raw_bytes).unwrap();
+        assert_eq!(loaded.sources, RoaringTreemap::from_iter([0, 1, 2, 3]));
+        assert_eq!(loaded.targets, RoaringTreemap::from_iter([100]));
+        assert!(loaded.source_status.is_empty() && loaded.target_status.is_empty());
+
+        // The serde implementation alone needs the statuses
+        let bincode_options = bincode::DefaultOptions::new().with_no_limit();
+        assert!(bincode_options
+            .deserialize::<ExperimentFeatureData>(raw_bytes)
+            .is_err());
+        let json = r#"{"sources": [], "targets": []}"#;
+        assert!(serde_json::from_str::<ExperimentFeatureData>(json).is_err());
+    }
+
+    // Deterministic xorshift, so failures can be reproduced
+    fn next_random(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    #[test]
+    fn test_experiment_feature_data_corrupt_bytes() {
+        let data = ExperimentFeatureData::new(
+            RoaringTreemap::from_iter([1, 5, 1 << 40]),
+            RoaringTreemap::from_iter(0..100),
+        );
+        let bincode_options = bincode::DefaultOptions::new().with_no_limit();
+        let valid = bincode_options.serialize(&data).unwrap();
+        assert!(ExperimentFeatureData::from_bytes(&valid).is_ok());
+
+        // Every prefix of a valid file, and the file with each byte replaced
+        let mut corpus: Vec<Vec<u8>> = (0..valid.len()).map(|end| valid[..end].to_vec()).collect();
+        for i in 0..valid.len() {
+            for value in [0x00, 0x01, 0x7f, 0x80, 0xff] {
+                let mut bytes = valid.clone();
+                bytes[i] = value;
+                corpus.push(bytes);
+            }
+        }
+        // Random byte strings, some starting with a plausible length prefix
+        let mut state = 0x2545_f491_4f6c_dd1d;
+        for _ in 0..2000 {
+            let len = (next_random(&mut state) % 256) as usize;
+            let mut bytes: Vec<u8> = (0..len).map(|_| next_random(&mut state) as u8).collect();
+            if len > 8 && next_random(&mut state).is_multiple_of(2) {
+                bytes[..8].copy_from_slice(&((len - 8) as u64).to_le_bytes());
+            }
+            corpus.push(bytes);
+        }
+
+        // Loading must not panic; whether it succeeds doesn't matter
+        for bytes in &corpus {
+            let _ = ExperimentFeatureData::from_bytes(bytes);
+        }
+
+        let json = r#"{"sources": [1, 2, 3], "targets": []}"#;
+        assert!(serde_json::from_str::<ExperimentFeatureData>(json).is_err());
     }
 }
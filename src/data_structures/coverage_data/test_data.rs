@@ -0,0 +1,96 @@
+use crate::data_structures::{
+    BucketLoc, ChromosomeData, CoverageData, DbID, Facet, FacetType, FeatureCoordinates,
+    FeatureLocation, ObservationData,
+};
+
+pub fn observation(reo_id: DbID, facet_value_ids: Vec<DbID>) -> ObservationData {
+    ObservationData {
+        reo_id,
+        facet_value_ids,
+        source_id: reo_id,
+        target_id: None,
+        effect_size: 0.0,
+        significance: 1.0,
+        neg_log_significance: 0.0,
+    }
+}
+
+pub fn categorical_facet(id: DbID, value_ids: &[DbID]) -> Facet {
+    Facet {
+        id,
+        name: format!("facet {}", id),
+        facet_type: FacetType::Categorical,
+        description: "".to_string(),
+        coverage: None,
+        range: None,
+        range64: None,
+        values: Some(
+            value_ids
+                .iter()
+                .map(|value_id| (*value_id, format!("value {}", value_id)))
+                .collect(),
+        ),
+        value_parents: None,
+        value_display: None,
+    }
+}
+
+// Four observations across two categorical facets: facet 1 has values 10, 11 and 12, and
+// facet 2 has values 20 and 21. The first two observations are significant. Each observation's
+// source has the observation's REO id; observations 0 and 2 also have targets 100 and 101.
+pub fn coverage_data() -> CoverageData {
+    let mut observations = vec![
+        observation(0, vec![10, 20]),
+        observation(1, vec![11, 20]),
+        observation(2, vec![12, 21]),
+        observation(3, vec![10, 21]),
+    ];
+    observations[0].target_id = Some(100);
+    observations[2].target_id = Some(101);
+    let nonsignificant_observations = observations.split_off(2);
+
+    let feature_buckets = [
+        (0, BucketLoc { chrom: 0, idx: 0 }),
+        (1, BucketLoc { chrom: 0, idx: 1 }),
+        (2, BucketLoc { chrom: 1, idx: 0 }),
+        (3, BucketLoc { chrom: 1, idx: 3 }),
+        (100, BucketLoc { chrom: 1, idx: 2 }),
+        (101, BucketLoc { chrom: 0, idx: 4 }),
+    ];
+
+    CoverageData::new(
+        observations,
+        nonsignificant_observations,
+        2_000_000,
+        vec![
+            ChromosomeData::from("chr1", 0),
+            ChromosomeData::from("chr2", 1),
+        ],
+        vec![
+            categorical_facet(1, &[10, 11, 12]),
+            categorical_facet(2, &[20, 21]),
+        ],
+        vec![10_000_000, 8_000_000],
+        feature_buckets.into_iter().collect(),
+    )
+}
+
+// A 100bp feature 100bp into the bucket of each feature of `coverage_data()`. Targets are named
+// GENE<id>; sources don't have names.
+pub fn feature_coordinates() -> FeatureCoordinates {
+    let data = coverage_data();
+    let mut coordinates = FeatureCoordinates::default();
+    for (feature_id, bucket) in &data.feature_buckets {
+        let start = bucket.idx as u64 * data.bucket_size as u64 + 100;
+        coordinates.insert(
+            *feature_id,
+            FeatureLocation {
+                chrom: bucket.chrom,
+                start,
+                end: start + 100,
+                name: (*feature_id >= 100).then(|| format!("GENE{}", feature_id)),
+            },
+        );
+    }
+    coordinates
+}
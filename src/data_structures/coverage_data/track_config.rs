@@ -0,0 +1,238 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use super::interactions::BEDPE_COLUMNS;
+use crate::data_structures::{BucketStatistic, CoverageData, DbID, InteractionFormat};
+
+// Colour used for features without a coloured facet value
+const DEFAULT_TRACK_COLOR: &str = "#888888";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrackFile {
+    // Written by `CoverageData::write_interactions` or `CoverageData::write_bedpe_with_facet`
+    Interactions(InteractionFormat),
+    // Written by `CoverageData::write_bedgraph`
+    BedGraph(BucketStatistic),
+}
+
+// An exported file, and how a genome browser should show it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TrackExport {
+    pub name: String,
+    pub url: String,
+    // The JBrowse assembly name, e.g. "hg38". igv.js track configurations can't name a genome;
+    // igv.js loads every track against the genome the browser was created with, so the caller
+    // has to create the browser with this genome.
+    pub genome: String,
+    pub file: TrackFile,
+    // A categorical facet to colour interactions by, using the colours of its values'
+    // `FacetValueDisplay`. Only BEDPE files written by `CoverageData::write_bedpe_with_facet`
+    // with this facet have the column the colours are looked up by, so other files aren't
+    // coloured.
+    #[serde(default)]
+    pub color_facet: Option<DbID>,
+}
+
+fn track_id(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+impl CoverageData {
+    // The facet's name and (value label, colour) pairs, in display order. Values without a
+    // colour are left out.
+    fn track_palette(&self, export: &TrackExport) -> Option<(&str, Vec<(&str, &str)>)> {
+        if export.file != TrackFile::Interactions(InteractionFormat::Bedpe) {
+            return None;
+        }
+        let facet_id = export.color_facet?;
+        let facet = self.facets.iter().find(|facet| facet.id == facet_id)?;
+        let palette = facet
+            .ordered_values()
+            .into_iter()
+            .filter_map(|(value_id, label)| {
+                let color = facet.display(value_id)?.color.as_deref()?;
+                Some((label, color))
+            })
+            .collect();
+        Some((facet.name.as_str(), palette))
+    }
+
+    // igv.js track configuration. igv.js can't read WashU longrange files, so there's no
+    // configuration for them.
+    pub fn igv_track_config(&self, export: &TrackExport) -> Option<Value> {
+        let mut config = match export.file {
+            TrackFile::Interactions(InteractionFormat::Longrange) => return None,
+            TrackFile::Interactions(format) => json!({
+                "type": "interact",
+                "format": if format == InteractionFormat::Bedpe { "bedpe" } else { "interact" },
+                "arcType": "nested",
+                "useScore": true,
+            }),
+            TrackFile::BedGraph(_) => json!({
+                "type": "wig",
+                "format": "bedgraph",
+                "autoscale": true,
+            }),
+        };
+        config["name"] = json!(export.name);
+        config["url"] = json!(export.url);
+
+        if let Some((field, palette)) = self.track_palette(export) {
+            let palette: Map<String, Value> = palette
+                .into_iter()
+                .map(|(label, color)| (label.to_string(), json!(color)))
+                .collect();
+            config["color"] = json!(DEFAULT_TRACK_COLOR);
+            config["colorBy"] = json!({ "field": field, "palette": palette });
+        }
+        Some(config)
+    }
+
+    // JBrowse 2 track configuration. JBrowse can only read UCSC interact files once they're
+    // converted to bigInteract, and can't read WashU longrange files, so there's no
+    // configuration for either.
+    pub fn jbrowse_track_config(&self, export: &TrackExport) -> Option<Value> {
+        let track_id = track_id(&export.name);
+        let mut config = match export.file {
+            TrackFile::Interactions(InteractionFormat::Bedpe) => json!({
+                "type": "FeatureTrack",
+                "adapter": {
+                    "type": "BedpeAdapter",
+                    "bedpeLocation": { "uri": export.url },
+                },
+                "displays": [{
+                    "type": "LinearArcDisplay",
+                    "displayId": format!("{}-LinearArcDisplay", track_id),
+                    "renderer": { "type": "ArcRenderer" },
+                }],
+            }),
+            TrackFile::Interactions(_) => return None,
+            TrackFile::BedGraph(_) => json!({
+                "type": "QuantitativeTrack",
+                "adapter": {
+                    "type": "BedGraphAdapter",
+                    "bedGraphLocation": { "uri": export.url },
+                },
+            }),
+        };
+        config["trackId"] = json!(track_id);
+        config["name"] = json!(export.name);
+        config["assemblyNames"] = json!([export.genome]);
+
+        if let Some((field, palette)) = self.track_palette(export) {
+            let mut column_names = BEDPE_COLUMNS.to_vec();
+            column_names.push(field);
+            config["adapter"]["columnNames"] = json!(column_names);
+
+            // A nested conditional, e.g.
+            // jexl:get(feature,'Direction')=='Enriched Only'?'#d62728':'#888888'
+            let mut color = format!("'{}'", DEFAULT_TRACK_COLOR);
+            for (label, value_color) in palette.into_iter().rev() {
+                color = format!(
+                    "get(feature,'{}')=='{}'?'{}':{}",
+                    field, label, value_color, color
+                );
+            }
+            config["displays"][0]["renderer"]["color"] = json!(format!("jexl:{}", color));
+        }
+        Some(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_hash::FxHashMap;
+    use serde_json::json;
+
+    use super::{TrackExport, TrackFile};
+    use crate::data_structures::coverage_data::test_data::coverage_data;
+    use crate::data_structures::{BucketStatistic, FacetValueDisplay, InteractionFormat};
+
+    #[test]
+    fn test_track_configs() {
+        let mut data = coverage_data();
+        let export = TrackExport {
+            name: "Screen hits".to_string(),
+            url: "https://example.org/hits.bedpe".to_string(),
+            genome: "hg38".to_string(),
+            file: TrackFile::Interactions(InteractionFormat::Bedpe),
+            color_facet: None,
+        };
+
+        let igv = data.igv_track_config(&export).unwrap();
+        assert_eq!(igv["type"], "interact");
+        assert_eq!(igv["format"], "bedpe");
+        assert_eq!(igv["url"], "https://example.org/hits.bedpe");
+        assert!(igv.get("genome").is_none());
+
+        let jbrowse = data.jbrowse_track_config(&export).unwrap();
+        assert_eq!(jbrowse["trackId"], "Screen_hits");
+        assert_eq!(jbrowse["assemblyNames"], json!(["hg38"]));
+        assert_eq!(
+            jbrowse["adapter"]["bedpeLocation"]["uri"],
+            "https://example.org/hits.bedpe"
+        );
+
+        let bedgraph = TrackExport {
+            file: TrackFile::BedGraph(BucketStatistic::Count),
+            ..export.clone()
+        };
+        assert_eq!(
+            data.igv_track_config(&bedgraph).unwrap()["format"],
+            "bedgraph"
+        );
+        assert_eq!(
+            data.jbrowse_track_config(&bedgraph).unwrap()["type"],
+            "QuantitativeTrack"
+        );
+
+        let longrange = TrackExport {
+            file: TrackFile::Interactions(InteractionFormat::Longrange),
+            ..export.clone()
+        };
+        assert!(data.igv_track_config(&longrange).is_none());
+        assert!(data.jbrowse_track_config(&longrange).is_none());
+
+        // Colouring by the values of facet 1. Value 12 doesn't have a colour.
+        let value_display: FxHashMap<_, _> = [(10, "#ff0000"), (11, "#0000ff")]
+            .into_iter()
+            .map(|(value_id, color)| {
+                let display = FacetValueDisplay {
+                    order: value_id as u32,
+                    color: Some(color.to_string()),
+                    label: None,
+                };
+                (value_id, display)
+            })
+            .collect();
+        data.facets[0].value_display = Some(value_display);
+        let colored = TrackExport {
+            color_facet: Some(1),
+            ..export
+        };
+
+        let igv = data.igv_track_config(&colored).unwrap();
+        assert_eq!(igv["color"], "#888888");
+        assert_eq!(
+            igv["colorBy"],
+            json!({"field": "facet 1", "palette": {"value 10": "#ff0000", "value 11": "#0000ff"}})
+        );
+
+        let jbrowse = data.jbrowse_track_config(&colored).unwrap();
+        assert_eq!(jbrowse["adapter"]["columnNames"][11], "facet 1");
+        assert_eq!(
+            jbrowse["displays"][0]["renderer"]["color"],
+            "jexl:get(feature,'facet 1')=='value 10'?'#ff0000':\
+             get(feature,'facet 1')=='value 11'?'#0000ff':'#888888'"
+        );
+
+        // Only BEDPE files have the facet column
+        let bedgraph = TrackExport {
+            file: TrackFile::BedGraph(BucketStatistic::Count),
+            ..colored
+        };
+        assert!(data.igv_track_config(&bedgraph).unwrap()["colorBy"].is_null());
+    }
+}
@@ -0,0 +1,242 @@
+use roaring::RoaringBitmap;
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::{Deserialize, Serialize};
+
+use crate::data_structures::{CoverageData, DbID};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VolcanoPoint {
+    pub reo_id: DbID,
+    pub effect_size: f32,
+    pub neg_log_significance: f64,
+    pub significant: bool,
+    // The observation's value of the category facet, if it has one
+    pub category: Option<DbID>,
+}
+
+// Thins out the nonsignificant points to at most `max_points` by binning them on a
+// grid_size x grid_size grid. Each occupied cell keeps one point while there's room, sparsest
+// cells first so outliers survive, and the rest of the room is shared out in proportion to
+// the cells' sizes, so the shape of the cloud is preserved. Significant points are always kept.
+// A grid_size of 0 is treated as a single cell.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct VolcanoDownsampling {
+    pub max_points: usize,
+    pub grid_size: usize,
+}
+
+impl VolcanoDownsampling {
+    fn sample(&self, points: Vec<(u32, f64, f64)>) -> Vec<u32> {
+        if points.len() <= self.max_points {
+            return points
+                .into_iter()
+                .map(|(position, _, _)| position)
+                .collect();
+        }
+
+        let (mut min_x, mut max_x, mut min_y, mut max_y) = (f64::MAX, f64::MIN, f64::MAX, f64::MIN);
+        for (_, x, y) in &points {
+            min_x = min_x.min(*x);
+            max_x = max_x.max(*x);
+            min_y = min_y.min(*y);
+            max_y = max_y.max(*y);
+        }
+        let grid_size = self.grid_size.max(1);
+        let cell = |value: f64, min: f64, max: f64| -> usize {
+            if max > min {
+                (((value - min) / (max - min)) * grid_size as f64).min((grid_size - 1) as f64)
+                    as usize
+            } else {
+                0
+            }
+        };
+
+        let mut cells: FxHashMap<(usize, usize), Vec<u32>> = FxHashMap::default();
+        let total = points.len();
+        for (position, x, y) in points {
+            cells
+                .entry((cell(x, min_x, max_x), cell(y, min_y, max_y)))
+                .or_default()
+                .push(position);
+        }
+
+        let mut cells: Vec<Vec<u32>> = cells.into_values().collect();
+        cells.sort_unstable_by_key(|positions| (positions.len(), positions[0]));
+        let kept_cells = cells.len().min(self.max_points);
+        // The room left after keeping one point of each cell, and the points it's shared by
+        let extra_room = self.max_points - kept_cells;
+        let extra_points = total - cells.len();
+
+        let mut sampled = vec![];
+        for positions in &cells[..kept_cells] {
+            let extra = ((positions.len() - 1) * extra_room)
+                .checked_div(extra_points)
+                .unwrap_or(0);
+            let keep = 1 + extra;
+            // Evenly spaced positions, so the sample doesn't depend on hashing order
+            sampled.extend((0..keep).map(|i| positions[i * positions.len() / keep]));
+        }
+        sampled.sort_unstable();
+        sampled
+    }
+}
+
+impl CoverageData {
+    // Volcano plot points (effect size vs. -log10 significance) for the observations at the
+    // given positions, e.g. the result of `CoverageData::filter`. Observations without an
+    // effect size or significance are left out.
+    pub fn volcano_points(
+        &self,
+        positions: &RoaringBitmap,
+        category_facet: Option<DbID>,
+        downsampling: Option<VolcanoDownsampling>,
+    ) -> Vec<VolcanoPoint> {
+        let category_values: FxHashSet<DbID> = category_facet
+            .and_then(|facet_id| self.facets.iter().find(|facet| facet.id == facet_id))
+            .and_then(|facet| facet.values.as_ref())
+            .map(|values| values.keys().copied().collect())
+            .unwrap_or_default();
+
+        let significant_count = self.significant_observations.len() as u32;
+        let mut significant = vec![];
+        let mut nonsignificant = vec![];
+        for position in positions {
+            let observation = match self.observation(position) {
+                Some(observation) => observation,
+                None => continue,
+            };
+            let (x, y) = (
+                observation.effect_size as f64,
+                observation.neg_log_significance,
+            );
+            if x.is_nan() || y.is_nan() {
+                continue;
+            }
+
+            if position < significant_count {
+                significant.push(position);
+            } else {
+                nonsignificant.push((position, x, y));
+            }
+        }
+
+        let nonsignificant = match downsampling {
+            Some(downsampling) => downsampling.sample(nonsignificant),
+            None => nonsignificant
+                .into_iter()
+                .map(|(position, _, _)| position)
+                .collect(),
+        };
+
+        significant
+            .into_iter()
+            .chain(nonsignificant)
+            .filter_map(|position| {
+                let observation = self.observation(position)?;
+                Some(VolcanoPoint {
+                    reo_id: observation.reo_id,
+                    effect_size: observation.effect_size,
+                    neg_log_significance: observation.neg_log_significance,
+                    significant: position < significant_count,
+                    category: observation
+                        .facet_value_ids
+                        .iter()
+                        .find(|value_id| category_values.contains(value_id))
+                        .copied(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use roaring::RoaringBitmap;
+
+    use super::VolcanoDownsampling;
+    use crate::data_structures::coverage_data::test_data::coverage_data;
+    use crate::data_structures::Filter;
+
+    #[test]
+    fn test_downsampling_keeps_sparse_cells() {
+        // A dense cluster near the origin and a single outlier
+        let mut points: Vec<(u32, f64, f64)> = (0..100)
+            .map(|i| (i, (i % 10) as f64 * 0.01, (i / 10) as f64 * 0.01))
+            .collect();
+        points.push((100, 5.0, 5.0));
+
+        let downsampling = VolcanoDownsampling {
+            max_points: 10,
+            grid_size: 4,
+        };
+        let sampled = downsampling.sample(points.clone());
+        assert!(sampled.len() <= 10);
+        assert!(sampled.contains(&100));
+
+        // More occupied cells than points to keep
+        let downsampling = VolcanoDownsampling {
+            max_points: 3,
+            grid_size: 10,
+        };
+        let sampled = downsampling.sample(points.clone());
+        assert_eq!(sampled.len(), 3);
+        assert!(sampled.contains(&100));
+
+        // Without a grid every point is in one cell, but the cap still applies
+        let downsampling = VolcanoDownsampling {
+            max_points: 5,
+            grid_size: 0,
+        };
+        assert_eq!(downsampling.sample(points).len(), 5);
+    }
+
+    #[test]
+    fn test_volcano_points() {
+        let mut data = coverage_data();
+        for (observation, (effect_size, neg_log_significance)) in data
+            .significant_observations
+            .iter_mut()
+            .chain(data.nonsignificant_observations.iter_mut())
+            .zip([(1.0, 3.0), (-1.0, 2.0), (0.1, 0.5), (f32::NAN, 0.2)])
+        {
+            observation.effect_size = effect_size;
+            observation.neg_log_significance = neg_log_significance;
+        }
+        let all = data.filter(&Filter::default());
+
+        // Observation 3 has no effect size
+        let points = data.volcano_points(&all, Some(2), None);
+        let summary: Vec<_> = points
+            .iter()
+            .map(|point| (point.reo_id, point.significant, point.category))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (0, true, Some(20)),
+                (1, true, Some(20)),
+                (2, false, Some(21))
+            ]
+        );
+        assert_eq!(points[1].effect_size, -1.0);
+        assert_eq!(points[1].neg_log_significance, 2.0);
+
+        // Unknown facets don't have categories
+        let points = data.volcano_points(&all, Some(9), None);
+        assert!(points.iter().all(|point| point.category.is_none()));
+
+        // Downsampling only drops nonsignificant points
+        let downsampling = VolcanoDownsampling {
+            max_points: 0,
+            grid_size: 4,
+        };
+        let points = data.volcano_points(&all, None, Some(downsampling));
+        let reo_ids: Vec<_> = points.iter().map(|point| point.reo_id).collect();
+        assert_eq!(reo_ids, [0, 1]);
+
+        let positions = RoaringBitmap::from_iter([1, 2]);
+        let points = data.volcano_points(&positions, None, Some(downsampling));
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].reo_id, 1);
+    }
+}
@@ -0,0 +1,191 @@
+use std::fmt;
+
+use roaring::RoaringBitmap;
+use rustc_hash::FxHashMap;
+use serde::de::{self, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{self as ser, SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
+
+use crate::data_structures::{DbID, ObservationData};
+
+// An inverted index from each categorical facet value to the positions of the observations
+// that have that value. Positions number the significant observations first, followed by the
+// nonsignificant observations (see `CoverageData::observation`).
+#[derive(Clone, Debug, Default)]
+pub struct FacetIndex {
+    pub observation_count: u32,
+    pub facet_values: FxHashMap<DbID, RoaringBitmap>,
+}
+
+const FACET_INDEX_FIELD_OBSERVATION_COUNT: &str = "observation_count";
+const FACET_INDEX_FIELD_FACET_VALUES: &str = "facet_values";
+
+impl FacetIndex {
+    pub fn new(observation_count: u32, facet_values: FxHashMap<DbID, RoaringBitmap>) -> Self {
+        FacetIndex {
+            observation_count,
+            facet_values,
+        }
+    }
+
+    pub fn build<'a>(observations: impl Iterator<Item = &'a ObservationData>) -> Self {
+        let mut observation_count = 0;
+        let mut facet_values: FxHashMap<DbID, RoaringBitmap> = FxHashMap::default();
+        for (position, observation) in observations.enumerate() {
+            for value_id in &observation.facet_value_ids {
+                facet_values
+                    .entry(*value_id)
+                    .or_default()
+                    .insert(position as u32);
+            }
+            observation_count += 1;
+        }
+
+        FacetIndex::new(observation_count, facet_values)
+    }
+
+    // Every observation position covered by the index
+    pub fn all(&self) -> RoaringBitmap {
+        let mut all = RoaringBitmap::new();
+        all.insert_range(0..self.observation_count);
+        all
+    }
+
+    pub fn with_value(&self, value_id: DbID) -> RoaringBitmap {
+        self.facet_values
+            .get(&value_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    // Observations that have at least one of the facet values
+    pub fn with_any(&self, value_ids: &[DbID]) -> RoaringBitmap {
+        let mut matches = RoaringBitmap::new();
+        for value_id in value_ids {
+            if let Some(positions) = self.facet_values.get(value_id) {
+                matches |= positions;
+            }
+        }
+        matches
+    }
+}
+
+impl Serialize for FacetIndex {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("FacetIndex", 2)?;
+        state.serialize_field(FACET_INDEX_FIELD_OBSERVATION_COUNT, &self.observation_count)?;
+
+        let mut facet_values = Vec::with_capacity(self.facet_values.len());
+        for (value_id, positions) in &self.facet_values {
+            let mut position_data = vec![];
+            positions
+                .serialize_into(&mut position_data)
+                .map_err(ser::Error::custom)?;
+            facet_values.push((*value_id, position_data));
+        }
+        state.serialize_field(FACET_INDEX_FIELD_FACET_VALUES, &facet_values)?;
+
+        state.end()
+    }
+}
+
+fn facet_values_from<E: de::Error>(
+    facet_values: Vec<(DbID, Vec<u8>)>,
+) -> Result<FxHashMap<DbID, RoaringBitmap>, E> {
+    facet_values
+        .into_iter()
+        .map(|(value_id, position_data)| {
+            RoaringBitmap::deserialize_from(&position_data[..])
+                .map(|positions| (value_id, positions))
+                .map_err(de::Error::custom)
+        })
+        .collect()
+}
+
+impl<'de> Deserialize<'de> for FacetIndex {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(field_identifier, rename_all = "snake_case")]
+        enum Field {
+            ObservationCount,
+            FacetValues,
+        }
+
+        struct FacetIndexVisitor;
+
+        impl<'de> Visitor<'de> for FacetIndexVisitor {
+            type Value = FacetIndex;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("struct FacetIndex")
+            }
+
+            fn visit_seq<V>(self, mut seq: V) -> Result<FacetIndex, V::Error>
+            where
+                V: SeqAccess<'de>,
+            {
+                let observation_count = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let facet_values: Vec<(DbID, Vec<u8>)> = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+
+                Ok(FacetIndex::new(
+                    observation_count,
+                    facet_values_from(facet_values)?,
+                ))
+            }
+
+            fn visit_map<V>(self, mut map: V) -> Result<FacetIndex, V::Error>
+            where
+                V: MapAccess<'de>,
+            {
+                let mut observation_count = None;
+                let mut facet_values: Option<Vec<(DbID, Vec<u8>)>> = None;
+                while let Some(key) = map.next_key()? {
+                    match key {
+                        Field::ObservationCount => {
+                            if observation_count.is_some() {
+                                return Err(de::Error::duplicate_field(
+                                    FACET_INDEX_FIELD_OBSERVATION_COUNT,
+                                ));
+                            }
+                            observation_count = Some(map.next_value()?);
+                        }
+                        Field::FacetValues => {
+                            if facet_values.is_some() {
+                                return Err(de::Error::duplicate_field(
+                                    FACET_INDEX_FIELD_FACET_VALUES,
+                                ));
+                            }
+                            facet_values = Some(map.next_value()?);
+                        }
+                    }
+                }
+
+                let observation_count = observation_count
+                    .ok_or_else(|| de::Error::missing_field(FACET_INDEX_FIELD_OBSERVATION_COUNT))?;
+                let facet_values = facet_values
+                    .ok_or_else(|| de::Error::missing_field(FACET_INDEX_FIELD_FACET_VALUES))?;
+
+                Ok(FacetIndex::new(
+                    observation_count,
+                    facet_values_from(facet_values)?,
+                ))
+            }
+        }
+
+        const FIELDS: &[&str] = &[
+            FACET_INDEX_FIELD_OBSERVATION_COUNT,
+            FACET_INDEX_FIELD_FACET_VALUES,
+        ];
+        deserializer.deserialize_struct("FacetIndex", FIELDS, FacetIndexVisitor)
+    }
+}
@@ -0,0 +1,162 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::{Deserialize, Serialize};
+
+use crate::data_structures::facets::facet_set;
+use crate::data_structures::{Facet, FacetCoverage, FacetError, FacetType, WellKnownFacet};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FacetDefinition {
+    pub name: String,
+    pub facet_type: FacetType,
+    pub coverage: FxHashSet<FacetCoverage>,
+    // The allowed values of a categorical facet. If missing, any value is allowed.
+    #[serde(default)]
+    pub values: Option<Vec<String>>,
+}
+
+// The facets an assay's coverage data can have. Schemas are stored as JSON, e.g.
+//
+// {"facets": [{"name": "Direction", "facet_type": "FacetType.CATEGORICAL",
+//              "coverage": ["Source"], "values": ["Enriched Only", "Depleted Only"]}]}
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FacetSchema {
+    pub facets: Vec<FacetDefinition>,
+}
+
+impl Default for FacetSchema {
+    // The schema of `facet_set()`
+    fn default() -> Self {
+        let experiment_facets = facet_set();
+        let facets = WellKnownFacet::ALL
+            .into_iter()
+            .filter_map(|facet| {
+                Some(FacetDefinition {
+                    name: facet.name().to_string(),
+                    facet_type: facet.facet_type(),
+                    coverage: experiment_facets.get(facet.name())?.clone(),
+                    values: None,
+                })
+            })
+            .collect();
+
+        FacetSchema { facets }
+    }
+}
+
+impl FacetSchema {
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    pub fn from_path(file_path: &PathBuf) -> Result<Self, serde_json::Error> {
+        let reader = BufReader::new(File::open(file_path).map_err(serde_json::Error::io)?);
+        serde_json::from_reader(reader)
+    }
+
+    pub fn definition(&self, name: &str) -> Option<&FacetDefinition> {
+        self.facets.iter().find(|facet| facet.name == name)
+    }
+
+    // The same shape as `facet_set()`
+    pub fn facet_set(&self) -> FxHashMap<&str, FxHashSet<FacetCoverage>> {
+        self.facets
+            .iter()
+            .map(|facet| (facet.name.as_str(), facet.coverage.clone()))
+            .collect()
+    }
+
+    // Checks that every facet is defined by the schema and matches its definition. Facets
+    // defined by the schema don't have to be present.
+    pub fn validate(&self, facets: &[Facet]) -> Result<(), Vec<FacetError>> {
+        let mut errors = vec![];
+        for facet in facets {
+            let definition = match self.definition(&facet.name) {
+                Some(definition) => definition,
+                None => {
+                    errors.push(FacetError::UnknownFacet(facet.name.clone()));
+                    continue;
+                }
+            };
+
+            if !definition.facet_type.is_compatible(facet.facet_type) {
+                errors.push(FacetError::WrongFacetType {
+                    facet: facet.name.clone(),
+                    expected: definition.facet_type,
+                    found: facet.facet_type,
+                });
+            }
+
+            if let Some(coverage) = &facet.coverage {
+                for facet_coverage in coverage.difference(&definition.coverage) {
+                    errors.push(FacetError::UnexpectedCoverage {
+                        facet: facet.name.clone(),
+                        coverage: *facet_coverage,
+                    });
+                }
+            }
+
+            if let (Some(allowed_values), Some(values)) = (&definition.values, &facet.values) {
+                for value in values.values() {
+                    if !allowed_values.contains(value) {
+                        errors.push(FacetError::UnknownValue {
+                            facet: facet.name.clone(),
+                            value: value.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_hash::FxHashSet;
+
+    use super::FacetSchema;
+    use crate::data_structures::{Facet, FacetCoverage, FacetError, FacetType};
+
+    #[test]
+    fn test_schema_validation() {
+        let schema = FacetSchema::from_json(
+            r#"{"facets": [{"name": "Direction", "facet_type": "FacetType.CATEGORICAL",
+                "coverage": ["Source"], "values": ["Enriched Only", "Depleted Only"]}]}"#,
+        )
+        .unwrap();
+
+        let mut direction = Facet {
+            id: 1,
+            name: "Direction".to_string(),
+            facet_type: FacetType::Categorical,
+            description: "".to_string(),
+            coverage: Some(FxHashSet::from_iter([FacetCoverage::Source])),
+            range: None,
+            range64: None,
+            values: Some([(2, "Enriched Only".to_string())].into_iter().collect()),
+            value_parents: None,
+            value_display: None,
+        };
+        assert!(schema.validate(&[direction.clone()]).is_ok());
+
+        direction.coverage = Some(FxHashSet::from_iter([FacetCoverage::Target]));
+        assert_eq!(
+            schema.validate(&[direction]),
+            Err(vec![FacetError::UnexpectedCoverage {
+                facet: "Direction".to_string(),
+                coverage: FacetCoverage::Target
+            }])
+        );
+
+        assert_eq!(FacetSchema::default().facets.len(), 7);
+    }
+}
@@ -1,3 +1,8 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de::{self, Deserializer};
+use serde::ser::Serializer;
 use serde::{Deserialize, Serialize};
 
 use rustc_hash::{FxHashMap, FxHashSet};
@@ -13,6 +18,254 @@ pub const FACET_RAW_P_VALUE: &str = "Raw p value";
 pub const FACET_GRNA_TYPE: &str = "gRNA Type";
 
 pub const FACET_TYPE_CATEGORICAL: &str = "FacetType.CATEGORICAL";
+pub const FACET_TYPE_NUMERIC: &str = "FacetType.NUMERIC";
+pub const FACET_TYPE_NUMERIC64: &str = "FacetType.NUMERIC64";
+pub const FACET_TYPE_BOOLEAN: &str = "FacetType.BOOLEAN";
+
+// Serialized as the same strings the facet type used to be stored as, e.g.
+// "FacetType.CATEGORICAL", so the facets of coverage files written before the format was
+// versioned can still be read (see `CoverageData::from_bytes`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FacetType {
+    Categorical,
+    // Values and `Facet::range` are f32s
+    Numeric,
+    // Values and `Facet::range64` are f64s
+    Numeric64,
+    Boolean,
+}
+
+impl FacetType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FacetType::Categorical => FACET_TYPE_CATEGORICAL,
+            FacetType::Numeric => FACET_TYPE_NUMERIC,
+            FacetType::Numeric64 => FACET_TYPE_NUMERIC64,
+            FacetType::Boolean => FACET_TYPE_BOOLEAN,
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, FacetType::Numeric | FacetType::Numeric64)
+    }
+
+    // Numeric facets were all stored as "FacetType.NUMERIC" regardless of width, so numeric
+    // types are compatible with each other
+    pub fn is_compatible(&self, other: FacetType) -> bool {
+        *self == other || (self.is_numeric() && other.is_numeric())
+    }
+}
+
+impl fmt::Display for FacetType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for FacetType {
+    type Err = FacetError;
+
+    // Accepts both the stored form ("FacetType.NUMERIC") and the bare name ("numeric")
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.strip_prefix("FacetType.").unwrap_or(s);
+        match name.to_ascii_uppercase().as_str() {
+            "CATEGORICAL" => Ok(FacetType::Categorical),
+            "NUMERIC" => Ok(FacetType::Numeric),
+            "NUMERIC64" => Ok(FacetType::Numeric64),
+            "BOOLEAN" => Ok(FacetType::Boolean),
+            _ => Err(FacetError::UnknownFacetType(s.to_string())),
+        }
+    }
+}
+
+impl Serialize for FacetType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for FacetType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let facet_type = String::deserialize(deserializer)?;
+        facet_type.parse().map_err(de::Error::custom)
+    }
+}
+
+// The facets the portal knows how to display. Serialized as the facet names.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum WellKnownFacet {
+    Direction,
+    EffectSize,
+    CcreCategory,
+    CcreOverlap,
+    Significance,
+    RawPValue,
+    GrnaType,
+}
+
+impl WellKnownFacet {
+    pub const ALL: [WellKnownFacet; 7] = [
+        WellKnownFacet::Direction,
+        WellKnownFacet::EffectSize,
+        WellKnownFacet::CcreCategory,
+        WellKnownFacet::CcreOverlap,
+        WellKnownFacet::Significance,
+        WellKnownFacet::RawPValue,
+        WellKnownFacet::GrnaType,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WellKnownFacet::Direction => FACET_DIRECTION,
+            WellKnownFacet::EffectSize => FACET_EFFECT_SIZE,
+            WellKnownFacet::CcreCategory => FACET_CCRE_CATEGORY,
+            WellKnownFacet::CcreOverlap => FACET_CCRE_OVERLAP,
+            WellKnownFacet::Significance => FACET_SIGNIFICANCE,
+            WellKnownFacet::RawPValue => FACET_RAW_P_VALUE,
+            WellKnownFacet::GrnaType => FACET_GRNA_TYPE,
+        }
+    }
+
+    pub fn facet_type(&self) -> FacetType {
+        match self {
+            WellKnownFacet::EffectSize => FacetType::Numeric,
+            // Significance is -log10 of the raw p value
+            WellKnownFacet::Significance | WellKnownFacet::RawPValue => FacetType::Numeric64,
+            WellKnownFacet::Direction
+            | WellKnownFacet::CcreCategory
+            | WellKnownFacet::CcreOverlap
+            | WellKnownFacet::GrnaType => FacetType::Categorical,
+        }
+    }
+}
+
+impl fmt::Display for WellKnownFacet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for WellKnownFacet {
+    type Err = FacetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        WellKnownFacet::ALL
+            .into_iter()
+            .find(|facet| facet.name() == s)
+            .ok_or_else(|| FacetError::UnknownFacet(s.to_string()))
+    }
+}
+
+impl Serialize for WellKnownFacet {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for WellKnownFacet {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(de::Error::custom)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FacetError {
+    UnknownFacetType(String),
+    UnknownFacet(String),
+    WrongFacetType {
+        facet: String,
+        expected: FacetType,
+        found: FacetType,
+    },
+    MissingValues(String),
+    UnexpectedValues(String),
+    UnexpectedCoverage {
+        facet: String,
+        coverage: FacetCoverage,
+    },
+    UnknownValue {
+        facet: String,
+        value: String,
+    },
+    InvalidHierarchy {
+        facet: String,
+        value_id: DbID,
+    },
+    WrongValueCount {
+        facet_id: DbID,
+        expected: usize,
+        found: usize,
+    },
+    DuplicateFacet(DbID),
+}
+
+impl fmt::Display for FacetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FacetError::UnknownFacetType(facet_type) => {
+                write!(f, "Unknown facet type \"{}\"", facet_type)
+            }
+            FacetError::UnknownFacet(name) => write!(f, "Unknown facet \"{}\"", name),
+            FacetError::WrongFacetType {
+                facet,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Facet \"{}\" should be {}, but is {}",
+                facet, expected, found
+            ),
+            FacetError::MissingValues(facet) => {
+                write!(f, "Categorical facet \"{}\" has no values", facet)
+            }
+            FacetError::UnexpectedValues(facet) => {
+                write!(f, "Non-categorical facet \"{}\" has values", facet)
+            }
+            FacetError::UnexpectedCoverage { facet, coverage } => {
+                write!(f, "Facet \"{}\" shouldn't cover {:?}", facet, coverage)
+            }
+            FacetError::InvalidHierarchy { facet, value_id } => write!(
+                f,
+                "Facet \"{}\" value {} has a parent outside the facet or in a cycle",
+                facet, value_id
+            ),
+            FacetError::WrongValueCount {
+                facet_id,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Facet {} should have {} values, one for each observation, but has {}",
+                facet_id, expected, found
+            ),
+            FacetError::DuplicateFacet(facet_id) => {
+                write!(f, "There's already a facet with id {}", facet_id)
+            }
+            FacetError::UnknownValue { facet, value } => {
+                write!(
+                    f,
+                    "Facet \"{}\" doesn't allow the value \"{}\"",
+                    facet, value
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for FacetError {}
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum FacetCoverage {
@@ -26,16 +279,161 @@ pub struct FacetRange(pub f32, pub f32);
 #[derive(Clone, Copy, Serialize, Deserialize, Debug)]
 pub struct FacetRange64(pub f64, pub f64);
 
+// How a facet value is shown in legends, so every view renders it the same way
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct FacetValueDisplay {
+    // Values are listed in ascending order
+    pub order: u32,
+    // A CSS colour, e.g. "#1f77b4"
+    pub color: Option<String>,
+    // A longer description of the value than the value itself
+    pub label: Option<String>,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Facet {
     pub id: DbID,
     pub name: String,
-    pub facet_type: String,
+    pub facet_type: FacetType,
     pub description: String,
     pub coverage: Option<FxHashSet<FacetCoverage>>,
     pub range: Option<FacetRange>,
     pub range64: Option<FacetRange64>,
     pub values: Option<FxHashMap<DbID, String>>,
+    // Maps child facet values to their parent value, e.g. dELS and pELS to "enhancer-like".
+    // Values without a parent are top-level values. Coverage files written before the format was
+    // versioned don't have hierarchies.
+    #[serde(default)]
+    pub value_parents: Option<FxHashMap<DbID, DbID>>,
+    // Like hierarchies, display settings are missing from older coverage files
+    #[serde(default)]
+    pub value_display: Option<FxHashMap<DbID, FacetValueDisplay>>,
+}
+
+impl Facet {
+    pub fn well_known(&self) -> Option<WellKnownFacet> {
+        self.name.parse().ok()
+    }
+
+    pub fn display(&self, value_id: DbID) -> Option<&FacetValueDisplay> {
+        self.value_display.as_ref()?.get(&value_id)
+    }
+
+    // The long label of the value if it has one, otherwise the value itself
+    pub fn label(&self, value_id: DbID) -> Option<&str> {
+        match self
+            .display(value_id)
+            .and_then(|display| display.label.as_ref())
+        {
+            Some(label) => Some(label),
+            None => self
+                .values
+                .as_ref()?
+                .get(&value_id)
+                .map(|value| value.as_str()),
+        }
+    }
+
+    // The facet's values in display order. Values without a display order come last, and
+    // ties are broken by the value.
+    pub fn ordered_values(&self) -> Vec<(DbID, &str)> {
+        let mut values: Vec<(DbID, &str)> = match &self.values {
+            Some(values) => values
+                .iter()
+                .map(|(value_id, value)| (*value_id, value.as_str()))
+                .collect(),
+            None => return vec![],
+        };
+        values.sort_by_key(|(value_id, value)| {
+            let order = self.display(*value_id).map(|display| display.order);
+            (order.is_none(), order, *value)
+        });
+        values
+    }
+
+    pub fn parent(&self, value_id: DbID) -> Option<DbID> {
+        self.value_parents.as_ref()?.get(&value_id).copied()
+    }
+
+    // The value and all of the values below it in the hierarchy
+    pub fn value_and_descendants(&self, value_id: DbID) -> Vec<DbID> {
+        let mut values = vec![value_id];
+        let value_parents = match &self.value_parents {
+            Some(value_parents) => value_parents,
+            None => return values,
+        };
+
+        let mut children: FxHashMap<DbID, Vec<DbID>> = FxHashMap::default();
+        for (child, parent) in value_parents {
+            children.entry(*parent).or_default().push(*child);
+        }
+
+        let mut i = 0;
+        while i < values.len() {
+            if let Some(value_children) = children.get(&values[i]) {
+                for child in value_children {
+                    if !values.contains(child) {
+                        values.push(*child);
+                    }
+                }
+            }
+            i += 1;
+        }
+        values
+    }
+
+    pub fn validate(&self) -> Result<(), FacetError> {
+        if let Some(well_known) = self.well_known() {
+            let expected = well_known.facet_type();
+            if !expected.is_compatible(self.facet_type) {
+                return Err(FacetError::WrongFacetType {
+                    facet: self.name.clone(),
+                    expected,
+                    found: self.facet_type,
+                });
+            }
+        }
+
+        match (self.facet_type, &self.values) {
+            (FacetType::Categorical, None) => {
+                return Err(FacetError::MissingValues(self.name.clone()))
+            }
+            (FacetType::Numeric | FacetType::Numeric64, Some(_)) => {
+                return Err(FacetError::UnexpectedValues(self.name.clone()))
+            }
+            _ => (),
+        }
+
+        if let Some(value_parents) = &self.value_parents {
+            let values = self.values.as_ref();
+            let is_value = |value_id| values.is_some_and(|values| values.contains_key(value_id));
+            for (child, parent) in value_parents {
+                if !is_value(child) || !is_value(parent) {
+                    return Err(FacetError::InvalidHierarchy {
+                        facet: self.name.clone(),
+                        value_id: *child,
+                    });
+                }
+
+                // Following the parents from any value has to reach a top-level value
+                let mut ancestor = *parent;
+                for _ in 0..value_parents.len() {
+                    match value_parents.get(&ancestor) {
+                        Some(grandparent) => ancestor = *grandparent,
+                        None => break,
+                    }
+                }
+                if value_parents.contains_key(&ancestor) {
+                    return Err(FacetError::InvalidHierarchy {
+                        facet: self.name.clone(),
+                        value_id: *child,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -46,35 +444,81 @@ pub struct FacetValue {
 }
 
 pub fn facet_set() -> FxHashMap<&'static str, FxHashSet<FacetCoverage>> {
-    let mut experiment_facets: FxHashMap<&str, FxHashSet<FacetCoverage>> = FxHashMap::default();
-    experiment_facets.insert(
-        FACET_DIRECTION,
-        FxHashSet::from_iter([FacetCoverage::Target, FacetCoverage::Source].into_iter()),
-    );
-    experiment_facets.insert(
-        FACET_EFFECT_SIZE,
-        FxHashSet::from_iter([FacetCoverage::Target, FacetCoverage::Source].into_iter()),
-    );
-    experiment_facets.insert(
-        FACET_CCRE_CATEGORY,
-        FxHashSet::from_iter([FacetCoverage::Target, FacetCoverage::Source].into_iter()),
-    );
-    experiment_facets.insert(
-        FACET_CCRE_OVERLAP,
-        FxHashSet::from_iter([FacetCoverage::Target, FacetCoverage::Source].into_iter()),
-    );
-    experiment_facets.insert(
-        FACET_SIGNIFICANCE,
-        FxHashSet::from_iter([FacetCoverage::Target, FacetCoverage::Source].into_iter()),
-    );
-    experiment_facets.insert(
-        FACET_RAW_P_VALUE,
-        FxHashSet::from_iter([FacetCoverage::Target, FacetCoverage::Source].into_iter()),
-    );
-    experiment_facets.insert(
-        FACET_GRNA_TYPE,
-        FxHashSet::from_iter([FacetCoverage::Target, FacetCoverage::Source].into_iter()),
-    );
-
-    experiment_facets
+    WellKnownFacet::ALL
+        .into_iter()
+        .map(|facet| {
+            (
+                facet.name(),
+                FxHashSet::from_iter([FacetCoverage::Target, FacetCoverage::Source]),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FacetType, FacetValueDisplay, WellKnownFacet, FACET_GRNA_TYPE};
+    use crate::data_structures::coverage_data::test_data::categorical_facet;
+
+    #[test]
+    fn test_legacy_strings_deserialize() {
+        let facet_type = bincode::serialize("FacetType.CATEGORICAL").unwrap();
+        let facet_type: FacetType = bincode::deserialize(&facet_type).unwrap();
+        assert_eq!(facet_type, FacetType::Categorical);
+        assert_eq!(
+            bincode::serialize(&facet_type).unwrap(),
+            bincode::serialize("FacetType.CATEGORICAL").unwrap()
+        );
+
+        let facet = bincode::serialize(FACET_GRNA_TYPE).unwrap();
+        let facet: WellKnownFacet = bincode::deserialize(&facet).unwrap();
+        assert_eq!(facet, WellKnownFacet::GrnaType);
+
+        let facet_type = bincode::serialize("FacetType.TEXT").unwrap();
+        assert!(bincode::deserialize::<FacetType>(&facet_type).is_err());
+    }
+
+    #[test]
+    fn test_value_display_order_and_labels() {
+        let mut facet = categorical_facet(1, &[10, 11, 12, 13]);
+        facet
+            .values
+            .as_mut()
+            .unwrap()
+            .insert(13, "value 0".to_string());
+        let display = |order, label: Option<&str>| FacetValueDisplay {
+            order,
+            color: None,
+            label: label.map(str::to_string),
+        };
+        facet.value_display = Some(
+            [
+                (12, display(1, Some("Twelve"))),
+                (11, display(1, None)),
+                (10, display(2, None)),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        // Ties in the display order, and values without one, are ordered by value
+        assert_eq!(
+            facet.ordered_values(),
+            [
+                (11, "value 11"),
+                (12, "value 12"),
+                (10, "value 10"),
+                (13, "value 0"),
+            ]
+        );
+
+        assert_eq!(facet.label(12), Some("Twelve"));
+        assert_eq!(facet.label(11), Some("value 11"));
+        assert_eq!(facet.label(13), Some("value 0"));
+        assert_eq!(facet.label(14), None);
+
+        facet.value_display = None;
+        assert_eq!(facet.ordered_values()[0], (13, "value 0"));
+        assert_eq!(facet.label(12), Some("value 12"));
+    }
 }
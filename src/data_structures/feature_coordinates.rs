@@ -0,0 +1,46 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::data_structures::DbID;
+
+// Where a feature is. Coordinates are 0-based and half-open, as in BED files.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeatureLocation {
+    pub chrom: u8, // Chromosome index, as in `ChromosomeData::index`
+    pub start: u64,
+    pub end: u64,
+    // e.g. the gene symbol of a gene
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+// Feature locations by feature ID. Tables are stored as JSON, e.g.
+//
+// {"features": {"12": {"chrom": 0, "start": 1000, "end": 1500, "name": "GATA1"}}}
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FeatureCoordinates {
+    pub features: FxHashMap<DbID, FeatureLocation>,
+}
+
+impl FeatureCoordinates {
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    pub fn from_path(file_path: &PathBuf) -> Result<Self, serde_json::Error> {
+        let reader = BufReader::new(File::open(file_path).map_err(serde_json::Error::io)?);
+        serde_json::from_reader(reader)
+    }
+
+    pub fn insert(&mut self, feature_id: DbID, location: FeatureLocation) {
+        self.features.insert(feature_id, location);
+    }
+
+    pub fn get(&self, feature_id: DbID) -> Option<&FeatureLocation> {
+        self.features.get(&feature_id)
+    }
+}
@@ -1,11 +1,27 @@
 mod chrom_data;
 mod coverage_data;
+mod facet_index;
+mod facet_schema;
 pub mod facets;
+mod feature_coordinates;
 mod regeffects;
 
 pub use chrom_data::ChromosomeData;
-pub use coverage_data::{CoverageData, ExperimentFeatureData};
-pub use facets::{Facet, FacetCoverage, FacetRange, FacetRange64, FacetValue};
+pub use coverage_data::{
+    adjust_p_values, BucketAggregate, BucketStatistic, ChromosomeFeatureDensity, ContactCell,
+    ContactMatrix, CooMatrix, CorrectionMethod, CoverageData, DenseMatrix, ExperimentFeatureData,
+    FacetRangeMismatch, FacetValueCount, FeatureSimilarity, FeatureStatus, Filter, FilterIntervals,
+    Histogram, InteractionFormat, Link, ManhattanMode, ManhattanPoint, NumericColumn, NumericField,
+    QqPlot, SignificanceCutoff, TrackExport, TrackFile, Viewport, VolcanoDownsampling,
+    VolcanoPoint,
+};
+pub use facet_index::FacetIndex;
+pub use facet_schema::{FacetDefinition, FacetSchema};
+pub use facets::{
+    Facet, FacetCoverage, FacetError, FacetRange, FacetRange64, FacetType, FacetValue,
+    FacetValueDisplay, WellKnownFacet,
+};
+pub use feature_coordinates::{FeatureCoordinates, FeatureLocation};
 pub use regeffects::{BucketLoc, ObservationData};
 
 pub type DbID = u64;
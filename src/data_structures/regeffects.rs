@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::data_structures::DbID;
+use crate::data_structures::{DbID, FacetCoverage};
 
 #[derive(Serialize, Deserialize, Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
 pub struct BucketLoc {
@@ -20,3 +20,12 @@ pub struct ObservationData {
     pub significance: f64,
     pub neg_log_significance: f64,
 }
+
+impl ObservationData {
+    pub fn feature_id(&self, coverage: FacetCoverage) -> Option<DbID> {
+        match coverage {
+            FacetCoverage::Source => Some(self.source_id),
+            FacetCoverage::Target => self.target_id,
+        }
+    }
+}